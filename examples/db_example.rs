@@ -6,7 +6,7 @@ use service_utils_rs::services::db;
 async fn main() {
     let settings = Settings::load("examples/config/services.toml").unwrap();
     // println!("{:?}", settings);
-    db::init_db(settings.surrealdb).await.unwrap();
+    db::init_db(settings.services.surrealdb).await.unwrap();
     let db = db::get_db();
     let a = db.query("SELECT * FROM user").await.unwrap();
     println!("{:?}", a);
@@ -7,7 +7,7 @@ use service_utils_rs::{error::Result, services::http::http_server};
 async fn main() -> Result<()> {
     let settings = Settings::load("examples/config/services.toml").unwrap();
     let router = Router::new();
-    http_server::start(settings.http.port, router)
+    http_server::start_with_cfg(&settings.services.http, router)
         .await
         .unwrap();
 
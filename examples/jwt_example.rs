@@ -7,7 +7,7 @@ fn main() -> Result<()> {
     let settings = Settings::load("examples/config/services.toml").unwrap();
     println!("{:?}", settings);
 
-    let jwt = Jwt::new(settings.jwt);
+    let jwt = Jwt::new(settings.services.jwt);
     let (token, r) = jwt.generate_token_pair("sub".to_string())?;
     println!("access token: {:?}", token);
     println!("reflesh token: {:?}", r);
@@ -16,7 +16,7 @@ use service_utils_rs::{
 #[tokio::main]
 async fn main() -> Result<()> {
     let settings = Settings::load("examples/config/services.toml").unwrap();
-    let jwt = Jwt::new(settings.jwt);
+    let jwt = Jwt::new(settings.services.jwt);
 
     let router = init_router();
     let router = Arc::new(router);
@@ -28,7 +28,7 @@ async fn main() -> Result<()> {
         }
     };
 
-    websocket::server::start(settings.websocket.port, router, token_validator)
+    websocket::server::start_with_cfg(&settings.services.websocket, router, token_validator)
         .await
         .unwrap();
     Ok(())
@@ -0,0 +1,24 @@
+use service_utils_rs::services::ServiceSettings;
+
+const USAGE: &str = "Usage: service-utils <command>
+
+Commands:
+  config-sample    Print a commented sample TOML config for the enabled services
+  config-schema    Print a JSON Schema for the enabled services";
+
+fn main() {
+    let command = std::env::args().nth(1);
+    match command.as_deref() {
+        Some("config-sample") => print!("{}", ServiceSettings::sample_toml()),
+        Some("config-schema") => {
+            let schema = ServiceSettings::json_schema();
+            println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+// cargo run --bin service-utils -- config-sample
@@ -1,5 +1,10 @@
+use std::backtrace::{Backtrace, BacktraceStatus};
+
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::utils::validate::ValidationErrors;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("io error: {0}")]
@@ -8,10 +13,17 @@ pub enum Error {
     #[error("auth error: {0}")]
     AuthError(String),
 
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+
     #[cfg(feature = "jwt")]
     #[error("JWT error: {0}")]
     JwtError(#[from] jsonwebtoken::errors::Error),
 
+    #[cfg(feature = "jwt")]
+    #[error("{0}")]
+    TokenError(#[from] crate::services::jwt::TokenError),
+
     #[cfg(feature = "websocket")]
     #[error("websocket error: {0}")]
     WsError(#[from] tokio_tungstenite::tungstenite::Error),
@@ -23,6 +35,9 @@ pub enum Error {
     #[error("url error: {0}")]
     UrlError(#[from] url::ParseError),
 
+    #[error("serde error: {0}")]
+    SerdeError(#[from] serde_json::Error),
+
     #[cfg(feature = "db")]
     #[error("db error: {0}")]
     DbError(#[from] surrealdb::Error),
@@ -30,6 +45,9 @@ pub enum Error {
     #[error("config error: {0}")]
     ConfigError(#[from] config::ConfigError),
 
+    #[error("{0}")]
+    ValidationError(ValidationErrors),
+
     #[error("{message:} ({line:}, {column})")]
     CustomError {
         message: String,
@@ -45,6 +63,410 @@ pub enum Error {
 
     #[error("system error: {0}")]
     SystemError(String),
+
+    /// An error annotated with the operation that failed, see [`ResultExt`].
+    #[error("{context}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<Error>,
+        backtrace: Box<Backtrace>,
+    },
 }
 
 pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// Broad class of an error, deciding how it is reported to callers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// The caller sent something invalid. Codes `1000 .. 2000`.
+    Client,
+    /// The caller is not authenticated or not allowed. Codes `2000 .. 3000`.
+    Auth,
+    /// A service we depend on failed. Codes `3000 .. 4000`.
+    Upstream,
+    /// A bug or misconfiguration on our side. Codes `4000 .. 5000` and anything else.
+    Internal,
+}
+
+impl ErrorCategory {
+    /// Returns the category a numeric error code belongs to.
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            1000 .. 2000 => ErrorCategory::Client,
+            2000 .. 3000 => ErrorCategory::Auth,
+            3000 .. 4000 => ErrorCategory::Upstream,
+            _ => ErrorCategory::Internal,
+        }
+    }
+
+    /// Returns the HTTP status code used for errors of this category.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            ErrorCategory::Client => 400,
+            ErrorCategory::Auth => 401,
+            ErrorCategory::Upstream => 502,
+            ErrorCategory::Internal => 500,
+        }
+    }
+}
+
+impl Error {
+    /// Returns the stable numeric code of the error.
+    ///
+    /// | Code | Variant |
+    /// |------|---------|
+    /// | 1001 | `UrlError` |
+    /// | 2001 | `AuthError` |
+    /// | 2002 | `JwtError` |
+    /// | 2003 | `TokenError` |
+    /// | 2004 | `PermissionDenied` |
+    /// | 3001 | `RequestError` |
+    /// | 3002 | `DbError` |
+    /// | 3003 | `WsError` |
+    /// | 4000 | `ErrorMessage` |
+    /// | 4001 | `IoError` |
+    /// | 4002 | `ConfigError` |
+    /// | 4003 | `ValidationError` |
+    /// | 4004 | `CustomError` |
+    /// | 4005 | `SystemError` |
+    /// | 4006 | `SerdeError` |
+    ///
+    /// `ErrorCode` carries its own code, which should follow the ranges of [`ErrorCategory`].
+    /// `Context` reports the code of the error it wraps.
+    pub fn code(&self) -> u16 {
+        match self {
+            Error::UrlError(_) => 1001,
+            Error::AuthError(_) => 2001,
+            #[cfg(feature = "jwt")]
+            Error::JwtError(_) => 2002,
+            #[cfg(feature = "jwt")]
+            Error::TokenError(_) => 2003,
+            Error::PermissionDenied(_) => 2004,
+            #[cfg(feature = "request")]
+            Error::RequestError(_) => 3001,
+            #[cfg(feature = "db")]
+            Error::DbError(_) => 3002,
+            #[cfg(feature = "websocket")]
+            Error::WsError(_) => 3003,
+            Error::ErrorMessage(_) => 4000,
+            Error::IoError(_) => 4001,
+            Error::ConfigError(_) => 4002,
+            Error::ValidationError(_) => 4003,
+            Error::CustomError { .. } => 4004,
+            Error::SystemError(_) => 4005,
+            Error::SerdeError(_) => 4006,
+            Error::ErrorCode(code) => *code,
+            Error::Context { source, .. } => source.code(),
+        }
+    }
+
+    /// Returns the category of the error, derived from its code.
+    pub fn category(&self) -> ErrorCategory {
+        ErrorCategory::from_code(self.code())
+    }
+
+    /// Returns the HTTP status code for the error.
+    ///
+    /// This is the status of its category, except for database errors which are reported as
+    /// `500` since the database is part of the service, and `PermissionDenied` which is `403`.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Error::PermissionDenied(_) => 403,
+            #[cfg(feature = "db")]
+            Error::DbError(_) => 500,
+            Error::Context { source, .. } => source.status_code(),
+            _ => self.category().status_code(),
+        }
+    }
+
+    /// Returns a message that is safe to show to callers.
+    ///
+    /// Client errors, `AuthError`, `PermissionDenied` and `TokenError` keep their message; other
+    /// auth, upstream and internal errors are replaced by a generic text so no internal detail
+    /// leaks. Contexts are never shown, as they may name internal operations.
+    pub fn user_message(&self) -> String {
+        match (self, self.category()) {
+            (Error::Context { source, .. }, _) => source.user_message(),
+            (Error::AuthError(message), _) => message.clone(),
+            (Error::PermissionDenied(message), _) => message.clone(),
+            #[cfg(feature = "jwt")]
+            (Error::TokenError(error), _) => error.to_string(),
+            (_, ErrorCategory::Client) => self.to_string(),
+            (_, ErrorCategory::Auth) => "authentication failed".to_string(),
+            (_, ErrorCategory::Upstream) => "upstream service unavailable".to_string(),
+            (_, ErrorCategory::Internal) => "internal server error".to_string(),
+        }
+    }
+
+    /// Returns the messages of the underlying causes, outermost first.
+    pub fn source_chain(&self) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            chain.push(cause.to_string());
+            source = cause.source();
+        }
+        chain
+    }
+
+    /// Returns the backtrace captured where the outermost context was added, if any.
+    ///
+    /// Backtraces are always captured in debug builds, and in release builds only when
+    /// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` enables them.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        match self {
+            Error::Context { backtrace, .. } if backtrace.status() == BacktraceStatus::Captured => {
+                Some(backtrace)
+            }
+            Error::Context { source, .. } => source.backtrace(),
+            _ => None,
+        }
+    }
+
+    /// Wraps the error with a description of the operation that failed.
+    pub fn context<C>(self, context: C) -> Self
+    where
+        C: Into<String>,
+    {
+        let backtrace = if cfg!(debug_assertions) {
+            Backtrace::force_capture()
+        } else {
+            Backtrace::capture()
+        };
+        Error::Context {
+            context: context.into(),
+            source: Box::new(self),
+            backtrace: Box::new(backtrace),
+        }
+    }
+}
+
+/// Adds context to the error of any result convertible into [`Error`].
+///
+/// # Example
+///
+/// ```
+/// use service_utils_rs::error::{Result, ResultExt};
+///
+/// fn parse(frame: &[u8], conn: u32) -> Result<serde_json::Value> {
+///     serde_json::from_slice(frame)
+///         .with_context(|| format!("decoding ws frame from conn {}", conn))
+/// }
+///
+/// let err = parse(b"{", 42).unwrap_err();
+/// assert_eq!(err.to_string(), "decoding ws frame from conn 42");
+/// assert_eq!(err.code(), 4006);
+/// ```
+pub trait ResultExt<T> {
+    /// Wraps the error with `context`.
+    fn context<C>(self, context: C) -> Result<T>
+    where
+        C: Into<String>;
+
+    /// Wraps the error with the context returned by `f`, which is only called on error.
+    fn with_context<C, F>(self, f: F) -> Result<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C;
+}
+
+impl<T, E> ResultExt<T> for core::result::Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn context<C>(self, context: C) -> Result<T>
+    where
+        C: Into<String>,
+    {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C, F>(self, f: F) -> Result<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|e| e.into().context(f()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_and_categories() {
+        let err = Error::UrlError(url::ParseError::EmptyHost);
+        assert_eq!(err.code(), 1001);
+        assert_eq!(err.category(), ErrorCategory::Client);
+        assert_eq!(err.status_code(), 400);
+
+        let err = Error::AuthError("token missing".to_string());
+        assert_eq!(err.category(), ErrorCategory::Auth);
+        assert_eq!(err.status_code(), 401);
+
+        let err = Error::SystemError("Too many connections".to_string());
+        assert_eq!(err.code(), 4005);
+        assert_eq!(err.category(), ErrorCategory::Internal);
+
+        assert_eq!(Error::ErrorCode(1404).category(), ErrorCategory::Client);
+        assert_eq!(Error::ErrorCode(7).category(), ErrorCategory::Internal);
+    }
+
+    #[test]
+    fn test_user_message_hides_internal_details() {
+        let err = Error::SystemError("db password rejected".to_string());
+        assert_eq!(err.user_message(), "internal server error");
+
+        let err = Error::AuthError("token missing".to_string());
+        assert_eq!(err.user_message(), "token missing");
+
+        let err = Error::UrlError(url::ParseError::EmptyHost);
+        assert_eq!(err.user_message(), "url error: empty host");
+    }
+
+    #[test]
+    fn test_source_chain() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "missing.toml");
+        let err = Error::IoError(io);
+        assert_eq!(err.source_chain(), ["missing.toml"]);
+        assert!(Error::ErrorCode(1).source_chain().is_empty());
+    }
+
+    #[test]
+    fn test_context() {
+        let result: Result<serde_json::Value> =
+            serde_json::from_slice(b"{").context("decoding ws frame from conn 42");
+        let err = result.unwrap_err();
+        assert_eq!(err.to_string(), "decoding ws frame from conn 42");
+        assert_eq!(err.code(), 4006);
+        assert_eq!(err.category(), ErrorCategory::Internal);
+        assert!(err.source_chain()[0].starts_with("serde error: EOF"));
+        assert_eq!(err.user_message(), "internal server error");
+        if cfg!(debug_assertions) {
+            assert!(err.backtrace().is_some());
+        }
+
+        let err = Err::<(), _>(Error::SystemError("boom".to_string()))
+            .context("loading users")
+            .with_context(|| "starting service")
+            .unwrap_err();
+        assert_eq!(err.source_chain(), ["loading users", "system error: boom"]);
+        assert_eq!(err.user_message(), "internal server error");
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_into_common_error() {
+        use crate::services::http::response::CommonError;
+
+        let err = Error::SystemError("db password rejected".to_string());
+        let common = CommonError::from(&err);
+        assert_eq!(common.code, 4005);
+        assert_eq!(common.message, "internal server error");
+        assert_eq!(CommonError::from(&Error::ErrorCode(40000)).code, i16::MAX);
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_expose_error_details() {
+        use axum::{Router, body::Body, http::Request, middleware, routing::get};
+        use tower::ServiceExt;
+
+        use crate::services::http::response::expose_error_details;
+
+        async fn body(router: Router) -> serde_json::Value {
+            let response = router
+                .oneshot(Request::get("/").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice(&body).unwrap()
+        }
+
+        let router = Router::new().route(
+            "/",
+            get(|| async { Err::<(), _>(Error::SystemError("disk full".to_string())) }),
+        );
+        let safe = body(router.clone()).await;
+        assert_eq!(safe["message"], "internal server error");
+
+        let detailed = body(router.layer(middleware::from_fn(expose_error_details))).await;
+        assert_eq!(detailed["code"], 4005);
+        assert_eq!(detailed["message"], "system error: disk full");
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_into_response() {
+        use axum::{http::StatusCode, response::IntoResponse};
+
+        use crate::services::http::response::{CommonError, Empty, ResponseResult};
+
+        let response = Error::UrlError(url::ParseError::EmptyHost).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], 1001);
+        assert_eq!(body["message"], "url error: empty host");
+
+        fn handler() -> ResponseResult<Empty> {
+            Err(Error::AuthError("token missing".to_string()))?
+        }
+        let (status, body) = handler().unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body.message, "token missing");
+
+        let io = std::io::Error::other("disk full");
+        let detailed = CommonError::detailed(&Error::ConfigError(config::ConfigError::Foreign(
+            Box::new(io),
+        )));
+        assert_eq!(detailed.code, 4002);
+        assert!(detailed.message.contains("disk full"));
+
+        #[cfg(feature = "jwt")]
+        {
+            use crate::services::jwt::TokenError;
+
+            let response = Error::from(TokenError::Expired).into_response();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                response.headers()[axum::http::header::WWW_AUTHENTICATE],
+                TokenError::Expired.www_authenticate()
+            );
+        }
+
+        let response = Error::PermissionDenied("requires \"admin\"\r\nX-Injected: 1".to_string())
+            .into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.headers()[axum::http::header::WWW_AUTHENTICATE],
+            "Bearer error=\"insufficient_scope\", error_description=\"requires \
+             \\\"admin\\\"X-Injected: 1\""
+        );
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn test_websocket_error_reply() {
+        use crate::services::websocket::{ErrorReply, JsonMessage};
+
+        let err = Error::AuthError("token expired".to_string());
+        let msg = JsonMessage::error("login", &err);
+        assert_eq!(msg.action, "login");
+        let reply: ErrorReply = serde_json::from_value(msg.data["error"].clone()).unwrap();
+        assert_eq!(
+            reply,
+            ErrorReply {
+                code: 2001,
+                message: "token expired".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{Error, Result},
+    services::{Grants, StoreFuture},
+    utils::crypto_util::{constant_time_eq, random_hex, to_hex},
+};
+
+/// A stored API key. Only a salted hash of its secret is kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub id: String,
+    pub subject: String,
+    #[serde(default)]
+    pub grants: Grants,
+    pub salt: String,
+    pub hash: String,
+    /// UNIX timestamp after which the key is rejected, `None` for keys that never expire.
+    pub expires_at: Option<usize>,
+}
+
+impl ApiKeyRecord {
+    fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|exp| exp < now())
+    }
+}
+
+/// Keeps the records of issued API keys, by id.
+pub trait ApiKeyStore: Send + Sync {
+    /// Saves `record`, replacing any record with the same id.
+    fn insert(&self, record: ApiKeyRecord) -> StoreFuture<'_, ()>;
+
+    /// Returns the record `id`, or `None` if there is none.
+    fn get(&self, id: String) -> StoreFuture<'_, Option<ApiKeyRecord>>;
+
+    /// Removes the record `id`, returning `false` if there was none.
+    fn remove(&self, id: String) -> StoreFuture<'_, bool>;
+}
+
+/// An [`ApiKeyStore`] kept in memory, for a single instance or tests.
+#[derive(Debug, Default)]
+pub struct InMemoryApiKeyStore {
+    records: Mutex<HashMap<String, ApiKeyRecord>>,
+}
+
+impl InMemoryApiKeyStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ApiKeyStore for InMemoryApiKeyStore {
+    fn insert(&self, record: ApiKeyRecord) -> StoreFuture<'_, ()> {
+        self.records
+            .lock()
+            .unwrap()
+            .insert(record.id.clone(), record);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn get(&self, id: String) -> StoreFuture<'_, Option<ApiKeyRecord>> {
+        let record = self.records.lock().unwrap().get(&id).cloned();
+        Box::pin(async { Ok(record) })
+    }
+
+    fn remove(&self, id: String) -> StoreFuture<'_, bool> {
+        let removed = self.records.lock().unwrap().remove(&id).is_some();
+        Box::pin(async move { Ok(removed) })
+    }
+}
+
+#[cfg(feature = "db")]
+pub use surreal::SurrealApiKeyStore;
+
+#[cfg(feature = "db")]
+mod surreal {
+    use surrealdb::{Surreal, engine::remote::ws::Client};
+
+    use super::{ApiKeyRecord, ApiKeyStore, StoreFuture};
+
+    /// An [`ApiKeyStore`] kept in SurrealDB, shared by every instance of a service.
+    #[derive(Debug, Clone)]
+    pub struct SurrealApiKeyStore {
+        db: Surreal<Client>,
+        table: String,
+    }
+
+    impl SurrealApiKeyStore {
+        /// Creates a store using the `api_key` table.
+        pub fn new(db: Surreal<Client>) -> Self {
+            Self::with_table(db, "api_key")
+        }
+
+        /// Creates a store using the given table.
+        pub fn with_table(db: Surreal<Client>, table: &str) -> Self {
+            Self {
+                db,
+                table: table.to_string(),
+            }
+        }
+    }
+
+    impl ApiKeyStore for SurrealApiKeyStore {
+        fn insert(&self, record: ApiKeyRecord) -> StoreFuture<'_, ()> {
+            Box::pin(async move {
+                let _: Option<ApiKeyRecord> = self
+                    .db
+                    .upsert((self.table.as_str(), record.id.as_str()))
+                    .content(record)
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn get(&self, id: String) -> StoreFuture<'_, Option<ApiKeyRecord>> {
+            Box::pin(async move { Ok(self.db.select((self.table.as_str(), id)).await?) })
+        }
+
+        fn remove(&self, id: String) -> StoreFuture<'_, bool> {
+            Box::pin(async move {
+                let record: Option<ApiKeyRecord> =
+                    self.db.delete((self.table.as_str(), id)).await?;
+                Ok(record.is_some())
+            })
+        }
+    }
+}
+
+/// Issues and verifies opaque API keys for clients that cannot use JWTs, such as cron jobs
+/// and partner integrations.
+///
+/// Keys look like `{prefix}_{id}_{secret}`. The id locates the record in the store; the
+/// secret is only ever stored as a salted SHA-256 hash and compared in constant time.
+pub struct ApiKeys {
+    store: Box<dyn ApiKeyStore>,
+    prefix: String,
+}
+
+impl ApiKeys {
+    /// Creates an issuer of keys starting with `prefix`, e.g. `sk_live`.
+    pub fn new(store: impl ApiKeyStore + 'static, prefix: &str) -> Self {
+        Self {
+            store: Box::new(store),
+            prefix: prefix.to_string(),
+        }
+    }
+
+    /// Issues a key for `subject`, valid for `ttl` seconds or forever if `None`.
+    ///
+    /// # Returns
+    /// The plaintext key. It cannot be recovered later, so hand it to the client right away.
+    pub async fn create(
+        &self,
+        subject: String,
+        grants: Grants,
+        ttl: Option<usize>,
+    ) -> Result<String> {
+        let id = random_hex(8);
+        let secret = random_hex(32);
+        let salt = random_hex(16);
+        let record = ApiKeyRecord {
+            id: id.clone(),
+            subject,
+            grants,
+            hash: hash(&salt, &secret),
+            salt,
+            expires_at: ttl.map(|ttl| now() + ttl),
+        };
+        self.store.insert(record).await?;
+        Ok(format!("{}_{}_{}", self.prefix, id, secret))
+    }
+
+    /// Verifies `key`, returning its record.
+    ///
+    /// Unknown, revoked, expired and malformed keys all fail with the same
+    /// [`Error::AuthError`].
+    pub async fn verify(&self, key: &str) -> Result<ApiKeyRecord> {
+        let invalid = || Error::AuthError("invalid api key".to_string());
+        let mut parts = key.rsplitn(3, '_');
+        let (Some(secret), Some(id), Some(prefix)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        if prefix != self.prefix {
+            return Err(invalid());
+        }
+        let record = self.store.get(id.to_string()).await?.ok_or_else(invalid)?;
+        let hash = hash(&record.salt, secret);
+        if !constant_time_eq(hash.as_bytes(), record.hash.as_bytes()) || record.is_expired() {
+            return Err(invalid());
+        }
+        Ok(record)
+    }
+
+    /// Revokes the key `id`, returning `false` if there was none.
+    pub async fn revoke(&self, id: &str) -> Result<bool> {
+        self.store.remove(id.to_string()).await
+    }
+}
+
+fn hash(salt: &str, secret: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(secret.as_bytes());
+    to_hex(&hasher.finalize())
+}
+
+fn now() -> usize {
+    Utc::now().timestamp() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_and_verify() {
+        let keys = ApiKeys::new(InMemoryApiKeyStore::new(), "sk_test");
+        let grants = Grants::new().with_scopes(["reports:read"]);
+        let key = keys
+            .create("cron".to_string(), grants.clone(), None)
+            .await
+            .unwrap();
+        assert!(key.starts_with("sk_test_"));
+
+        let record = keys.verify(&key).await.unwrap();
+        assert_eq!(record.subject, "cron");
+        assert_eq!(record.grants, grants);
+        assert!(!record.hash.contains(key.rsplit('_').next().unwrap()));
+
+        let mut tampered = key.clone();
+        tampered.pop();
+        tampered.push(if key.ends_with('0') { '1' } else { '0' });
+        assert!(keys.verify(&tampered).await.is_err());
+        assert!(
+            keys.verify(&key.replacen("sk_test", "sk_live", 1))
+                .await
+                .is_err()
+        );
+        assert!(keys.verify("garbage").await.is_err());
+
+        assert!(keys.revoke(&record.id).await.unwrap());
+        assert!(keys.verify(&key).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_expired_key() {
+        let store = InMemoryApiKeyStore::new();
+        let keys = ApiKeys::new(store, "sk");
+        let key = keys
+            .create("cron".to_string(), Grants::new(), Some(60))
+            .await
+            .unwrap();
+        let mut record = keys.verify(&key).await.unwrap();
+        record.expires_at = Some(now() - 1);
+        keys.store.insert(record).await.unwrap();
+        let err = keys.verify(&key).await.unwrap_err();
+        assert_eq!(err.to_string(), "auth error: invalid api key");
+    }
+}
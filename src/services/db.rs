@@ -7,7 +7,13 @@ use surrealdb::{
     opt::auth::Root,
 };
 
-use crate::error::Result;
+use crate::{
+    error::Result,
+    utils::{
+        Secret, Validate, ValidationErrors,
+        config_schema::{ConfigField, ConfigSchema, FieldKind},
+    },
+};
 
 /// Struct representing the Surrealdb configuration parameters.
 #[derive(Debug, Deserialize)]
@@ -15,11 +21,48 @@ pub struct SurrealdbCfg {
     pub host: String,
     pub port: u16,
     pub username: String,
-    pub password: String,
+    pub password: Secret<String>,
     pub namespace: String,
     pub database: String,
 }
 
+impl Validate for SurrealdbCfg {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        errors.ensure(!self.host.is_empty(), "host", "must not be empty");
+        errors.ensure(self.port != 0, "port", "must not be 0");
+        errors.ensure(!self.username.is_empty(), "username", "must not be empty");
+        errors.ensure(!self.namespace.is_empty(), "namespace", "must not be empty");
+        errors.ensure(!self.database.is_empty(), "database", "must not be empty");
+    }
+}
+
+impl ConfigSchema for SurrealdbCfg {
+    fn description() -> &'static str {
+        "SurrealDB connection."
+    }
+
+    fn fields() -> Vec<ConfigField> {
+        vec![
+            ConfigField::required("host", FieldKind::String, "Server host.", "\"localhost\""),
+            ConfigField::required("port", FieldKind::Port, "Server port.", "8000"),
+            ConfigField::required("username", FieldKind::String, "Root user name.", "\"root\""),
+            ConfigField::required(
+                "password",
+                FieldKind::Secret,
+                "Root user password.",
+                "\"env://SURREALDB_PASSWORD\"",
+            ),
+            ConfigField::required(
+                "namespace",
+                FieldKind::String,
+                "Namespace to use.",
+                "\"dev\"",
+            ),
+            ConfigField::required("database", FieldKind::String, "Database to use.", "\"dev\""),
+        ]
+    }
+}
+
 static DB: LazyLock<Surreal<Client>> = LazyLock::new(Surreal::init);
 
 pub async fn init_db(cfg: SurrealdbCfg) -> Result<()> {
@@ -27,7 +70,7 @@ pub async fn init_db(cfg: SurrealdbCfg) -> Result<()> {
     DB.connect::<Ws>(addr).await?;
     DB.signin(Root {
         username: &cfg.username,
-        password: &cfg.password,
+        password: cfg.password.expose_secret(),
     })
     .await?;
     DB.use_ns(cfg.namespace).use_db(cfg.database).await?;
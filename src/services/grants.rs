@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Scopes and roles granted to a caller, usually read from its access token.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Grants {
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl Grants {
+    /// Creates empty grants.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds scopes, e.g. `orders:write`.
+    pub fn with_scopes<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes.extend(scopes.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds roles, e.g. `admin`.
+    pub fn with_roles<I, S>(mut self, roles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.roles.extend(roles.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns `true` if `scope` is granted.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// Returns `true` if `role` is granted.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    /// Fails with [`Error::PermissionDenied`] unless every scope of `required` is granted.
+    pub fn require_scopes<S>(&self, required: &[S]) -> Result<()>
+    where
+        S: AsRef<str>,
+    {
+        match required
+            .iter()
+            .find(|scope| !self.has_scope(scope.as_ref()))
+        {
+            Some(scope) => Err(Error::PermissionDenied(format!(
+                "missing scope {}",
+                scope.as_ref()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails with [`Error::PermissionDenied`] unless `role` is granted.
+    pub fn require_role(&self, role: &str) -> Result<()> {
+        if self.has_role(role) {
+            Ok(())
+        } else {
+            Err(Error::PermissionDenied(format!("missing role {}", role)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_require() {
+        let grants = Grants::new()
+            .with_scopes(["orders:read", "orders:write"])
+            .with_roles(["admin"]);
+        assert!(grants.require_scopes(&["orders:write"]).is_ok());
+        assert!(grants.require_scopes::<&str>(&[]).is_ok());
+        assert!(grants.require_role("admin").is_ok());
+
+        let err = grants
+            .require_scopes(&["orders:read", "users:write"])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "permission denied: missing scope users:write"
+        );
+        assert_eq!(err.status_code(), 403);
+        assert!(Grants::new().require_role("admin").is_err());
+    }
+}
@@ -1,10 +1,80 @@
-use axum::Router;
+use axum::{Router, middleware};
+use serde::Deserialize;
 
-use crate::error::Result;
+use super::response::expose_error_details;
+use crate::{
+    error::Result,
+    utils::{
+        Validate, ValidationErrors,
+        config_schema::{ConfigField, ConfigSchema, FieldKind},
+    },
+};
+
+/// Struct representing the http server configuration parameters.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpCfg {
+    /// Address to bind to, `0.0.0.0` by default.
+    #[serde(default = "default_host")]
+    pub host: String,
+    pub port: u16,
+    /// Include internal error details in error responses. Enable only for development.
+    #[serde(default)]
+    pub expose_error_details: bool,
+}
+
+fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+impl ConfigSchema for HttpCfg {
+    fn description() -> &'static str {
+        "HTTP server."
+    }
+
+    fn fields() -> Vec<ConfigField> {
+        vec![
+            ConfigField::optional(
+                "host",
+                FieldKind::String,
+                "Address to bind to.",
+                "\"0.0.0.0\"",
+            ),
+            ConfigField::required("port", FieldKind::Port, "Port to listen on.", "18130"),
+            ConfigField::optional(
+                "expose_error_details",
+                FieldKind::Boolean,
+                "Include internal error details in error responses.",
+                "false",
+            ),
+        ]
+    }
+}
+
+impl Validate for HttpCfg {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        errors.ensure(!self.host.is_empty(), "host", "must not be empty");
+        errors.ensure(self.port != 0, "port", "must not be 0");
+    }
+}
 
 pub async fn start(port: u16, router: Router) -> Result<()> {
-    let addr = format!("0.0.0.0:{}", port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    serve(&format!("0.0.0.0:{}", port), router).await
+}
+
+/// Starts the HTTP server on the host and port from `cfg`.
+///
+/// With `cfg.expose_error_details`, also layers [`expose_error_details`] over `router`.
+pub async fn start_with_cfg(cfg: &HttpCfg, router: Router) -> Result<()> {
+    let router = if cfg.expose_error_details {
+        router.layer(middleware::from_fn(expose_error_details))
+    } else {
+        router
+    };
+    serve(&format!("{}:{}", cfg.host, cfg.port), router).await
+}
+
+async fn serve(addr: &str, router: Router) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
     println!("HTTP Server is running on http://{}", addr);
     axum::serve(listener, router).await.unwrap();
     Ok(())
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, routing::get};
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::services::jwt::Jwt;
+
+/// Conventional path of the JWKS document.
+pub const JWKS_PATH: &str = "/.well-known/jwks.json";
+
+/// Serves the public keys of `jwt` as a JWKS document.
+pub async fn jwks_handler(State(jwt): State<Arc<Jwt>>) -> Json<JwkSet> {
+    Json(jwt.jwks())
+}
+
+/// Creates a router serving the JWKS document of `jwt` at [`JWKS_PATH`].
+pub fn jwks_router(jwt: Arc<Jwt>) -> Router {
+    Router::new()
+        .route(JWKS_PATH, get(jwks_handler))
+        .with_state(jwt)
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::Algorithm;
+
+    use super::*;
+    use crate::{services::jwt::JwtCfg, utils::ConfigDuration};
+
+    #[tokio::test]
+    async fn test_jwks_handler() {
+        let jwt = Arc::new(Jwt::new(JwtCfg {
+            algorithm: Algorithm::EdDSA,
+            public_key_path: Some("tests/keys/ed_public.pem".to_string()),
+            kid: Some("ed-1".to_string()),
+            audience: "test_audience".to_string(),
+            access_token_duration: ConfigDuration::from_secs(3600),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            ..Default::default()
+        }));
+
+        let Json(jwks) = jwks_handler(State(jwt)).await;
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(jwks.keys[0].common.key_id.as_deref(), Some("ed-1"));
+    }
+}
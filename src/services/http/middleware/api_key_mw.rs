@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use super::{UserId, challenge};
+use crate::{error::Error, services::api_key::ApiKeys};
+
+/// Header carrying an API key.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Verifies the `X-Api-Key` header against the `Arc<ApiKeys>` extension and inserts the
+/// `UserId` and `Grants` of the key into the request extensions, like `auth_mw::auth`.
+pub async fn api_key_auth(mut req: Request, next: Next) -> Result<Response, Response> {
+    let key = api_key(&req).ok_or_else(|| challenge("ApiKey".to_string()))?;
+    authenticate(&mut req, &key).await?;
+    Ok(next.run(req).await)
+}
+
+/// Accepts either `Authorization: Bearer <jwt>`, validated like `auth_mw::auth`, or
+/// `X-Api-Key`, validated like [`api_key_auth`].
+///
+/// A bearer token takes precedence when both are sent. Both identities land in the same
+/// `UserId` and `Grants` extensions, so handlers do not need to know which one was used.
+#[cfg(feature = "jwt")]
+pub async fn auth_or_api_key(mut req: Request, next: Next) -> Result<Response, Response> {
+    if let Some(token) = super::auth_mw::parse_token(req.headers()) {
+        super::auth_mw::authenticate::<()>(&mut req, &token)?;
+    } else if let Some(key) = api_key(&req) {
+        authenticate(&mut req, &key).await?;
+    } else {
+        return Err(challenge("Bearer".to_string()));
+    }
+    Ok(next.run(req).await)
+}
+
+/// Returns the API key of the request, or `None` if it has none.
+fn api_key(req: &Request) -> Option<String> {
+    let key = req.headers().get(API_KEY_HEADER)?.to_str().ok()?;
+    Some(key.to_string())
+}
+
+async fn authenticate(req: &mut Request, key: &str) -> Result<(), Response> {
+    let keys = req
+        .extensions()
+        .get::<Arc<ApiKeys>>()
+        .cloned()
+        .ok_or_else(|| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    let record = keys.verify(key).await.map_err(|e| match e {
+        Error::AuthError(_) => challenge("ApiKey".to_string()),
+        e => e.into_response(),
+    })?;
+    req.extensions_mut().insert(UserId(record.subject));
+    req.extensions_mut().insert(record.grants);
+    Ok(())
+}
+
+#[cfg(all(test, feature = "jwt"))]
+mod tests {
+    use axum::{
+        Extension, Router,
+        body::{Body, to_bytes},
+        http::header,
+        middleware,
+        routing::get,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        services::{
+            Grants,
+            api_key::InMemoryApiKeyStore,
+            jwt::{Jwt, JwtCfg},
+        },
+        utils::ConfigDuration,
+    };
+
+    async fn call(router: &Router, header: Option<(&str, &str)>) -> (StatusCode, String) {
+        let mut request = Request::builder().uri("/");
+        if let Some((name, value)) = header {
+            request = request.header(name, value);
+        }
+        let response = router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_auth_or_api_key() {
+        let jwt = Arc::new(Jwt::new(JwtCfg {
+            access_secret: "access_secret".into(),
+            refresh_secret: "refresh_secret".into(),
+            audience: "test_audience".to_string(),
+            access_token_duration: ConfigDuration::from_secs(3600),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            access_key_validate_exp: true,
+            refresh_key_validate_exp: true,
+            ..Default::default()
+        }));
+        let keys = Arc::new(ApiKeys::new(InMemoryApiKeyStore::new(), "sk"));
+        let token = jwt.generate_access_token("user".to_string()).unwrap();
+        let key = keys
+            .create(
+                "partner".to_string(),
+                Grants::new().with_scopes(["reports:read"]),
+                None,
+            )
+            .await
+            .unwrap();
+        let router = Router::new()
+            .route(
+                "/",
+                get(
+                    |Extension(UserId(sub)): Extension<UserId>,
+                     Extension(grants): Extension<Grants>| async move {
+                        format!("{} {}", sub, grants.scopes.join(" "))
+                    },
+                ),
+            )
+            .layer(middleware::from_fn(auth_or_api_key))
+            .layer(Extension(jwt))
+            .layer(Extension(keys));
+
+        let bearer = format!("Bearer {}", token);
+        let response = call(&router, Some((header::AUTHORIZATION.as_str(), &bearer))).await;
+        assert_eq!(response, (StatusCode::OK, "user ".to_string()));
+
+        let response = call(&router, Some((API_KEY_HEADER, &key))).await;
+        assert_eq!(
+            response,
+            (StatusCode::OK, "partner reports:read".to_string())
+        );
+
+        let response = call(&router, Some((API_KEY_HEADER, "sk_0_0"))).await;
+        assert_eq!(response.0, StatusCode::UNAUTHORIZED);
+
+        let response = call(&router, None).await;
+        assert_eq!(response.0, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -4,41 +4,130 @@ use axum::{
     extract::Request,
     http::{HeaderMap, StatusCode, header},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
+use serde::de::DeserializeOwned;
 
-use crate::services::jwt::Jwt;
+pub use super::UserId;
+use super::challenge;
+use crate::{
+    error::Error,
+    services::jwt::{Claims, Jwt},
+};
+
+/// Validates the bearer token and inserts its `UserId`, `Grants` and `Claims` into the request
+/// extensions.
+///
+/// Revoked tokens are rejected when the `Jwt` has a revocation store. Rejections are `401`
+/// responses whose `WWW-Authenticate` header tells why, as defined by RFC 6750.
+pub async fn auth(req: Request, next: Next) -> Result<Response, Response> {
+    auth_with::<()>(req, next).await
+}
 
-#[derive(Debug, Clone)]
-pub struct UserId(pub String);
+/// Like [`auth`], but decodes the application claims of the token as `C` and inserts
+/// `Claims<C>`, e.g. `middleware::from_fn(auth_with::<MyClaims>)`.
+pub async fn auth_with<C>(mut req: Request, next: Next) -> Result<Response, Response>
+where
+    C: DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    let token = parse_token(req.headers()).ok_or_else(|| challenge("Bearer".to_string()))?;
+    authenticate::<C>(&mut req, &token)?;
+    Ok(next.run(req).await)
+}
 
-pub async fn auth(mut req: Request, next: Next) -> Result<Response, StatusCode> {
-    let headers = req.headers();
-    let token = parse_token(headers)?;
+/// Validates `token` and inserts its identity into the request extensions.
+pub(super) fn authenticate<C>(req: &mut Request, token: &str) -> Result<(), Response>
+where
+    C: DeserializeOwned + Clone + Send + Sync + 'static,
+{
     let jwt = req
         .extensions()
         .get::<Arc<Jwt>>()
-        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        .ok_or_else(|| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    // Only token errors are the caller's fault; others, such as unusable keys, are server errors.
     let claims = jwt
-        .validate_access_token(&token)
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
-    let user_id = UserId(claims.sub);
+        .validate_access_token_with::<C>(token)
+        .map_err(|e| match e {
+            Error::TokenError(error) => challenge(error.www_authenticate()),
+            e => e.into_response(),
+        })?;
+    let user_id = UserId(claims.sub.clone());
     req.extensions_mut().insert(user_id);
-
-    Ok(next.run(req).await)
+    req.extensions_mut().insert(claims.grants());
+    req.extensions_mut().insert::<Claims<C>>(claims);
+    Ok(())
 }
 
-fn parse_token(headers: &HeaderMap) -> Result<String, StatusCode> {
-    let authorization = headers
-        .get(header::AUTHORIZATION)
-        .ok_or_else(|| StatusCode::UNAUTHORIZED)?;
+/// Returns the bearer token of the request, or `None` if it has none.
+pub(super) fn parse_token(headers: &HeaderMap) -> Option<String> {
+    let authorization = headers.get(header::AUTHORIZATION)?;
 
-    let mut parts = authorization.to_str().unwrap().splitn(2, ' ');
+    let mut parts = authorization.to_str().ok()?.splitn(2, ' ');
     match parts.next() {
         Some(scheme) if scheme == "Bearer" => {}
-        _ => return Err(StatusCode::UNAUTHORIZED),
+        _ => return None,
     }
 
-    let token = parts.next().ok_or(StatusCode::UNAUTHORIZED)?;
-    Ok(token.to_string())
+    let token = parts.next()?;
+    Some(token.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Extension, Router, body::Body, middleware, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        services::jwt::{JwtCfg, TokenError},
+        utils::ConfigDuration,
+    };
+
+    async fn call(router: &Router, authorization: Option<&str>) -> Response {
+        let mut request = Request::builder().uri("/");
+        if let Some(authorization) = authorization {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+        router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rejections() {
+        let jwt = Arc::new(Jwt::new(JwtCfg {
+            access_secret: "access_secret".into(),
+            refresh_secret: "refresh_secret".into(),
+            audience: "test_audience".to_string(),
+            access_token_duration: ConfigDuration::from_secs(3600),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            access_key_validate_exp: true,
+            refresh_key_validate_exp: true,
+            ..Default::default()
+        }));
+        let token = jwt.generate_access_token("test_sub".to_string()).unwrap();
+        let router = Router::new()
+            .route(
+                "/",
+                get(|Extension(UserId(sub)): Extension<UserId>| async move { sub }),
+            )
+            .layer(middleware::from_fn(auth))
+            .layer(Extension(jwt));
+
+        let response = call(&router, Some(&format!("Bearer {}", token))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = call(&router, None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+
+        let response = call(&router, Some("Bearer not-a-jwt")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[header::WWW_AUTHENTICATE],
+            TokenError::Malformed.www_authenticate()
+        );
+    }
 }
@@ -1,4 +1,28 @@
 pub mod cors;
+pub mod scope_mw;
 
 #[cfg(feature = "jwt")]
 pub mod auth_mw;
+
+#[cfg(feature = "api_key")]
+pub mod api_key_mw;
+
+#[cfg(any(feature = "jwt", feature = "api_key"))]
+use axum::{
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+
+/// Subject of the authenticated caller, inserted into the request extensions.
+#[derive(Debug, Clone)]
+pub struct UserId(pub String);
+
+/// Builds a `401` response carrying a `WWW-Authenticate` challenge.
+#[cfg(any(feature = "jwt", feature = "api_key"))]
+fn challenge(www_authenticate: String) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, www_authenticate)],
+    )
+        .into_response()
+}
@@ -0,0 +1,147 @@
+use std::{future::Future, marker::PhantomData, pin::Pin, sync::Arc};
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::request::Parts,
+    middleware::{FromFnLayer, Next, from_fn_with_state},
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    error::{Error, Result},
+    services::Grants,
+};
+
+/// Scopes a route requires, see [`require_scopes`].
+#[derive(Debug, Clone)]
+pub struct RequiredScopes(Arc<[String]>);
+
+type ScopeCheck =
+    fn(State<RequiredScopes>, Request, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>;
+
+/// Layer returned by [`require_scopes`].
+pub type RequireScopesLayer =
+    FromFnLayer<ScopeCheck, RequiredScopes, (State<RequiredScopes>, Request)>;
+
+/// Creates a layer rejecting requests whose `Grants` lack one of `scopes` with `403`.
+///
+/// Must run after the `auth` middleware, which inserts the `Grants` of the token, e.g.
+/// `Router::new().route(..).route_layer(require_scopes(["orders:write"]))` with `auth` layered
+/// on top.
+pub fn require_scopes<I, S>(scopes: I) -> RequireScopesLayer
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let required = RequiredScopes(scopes.into_iter().map(Into::into).collect());
+    from_fn_with_state(required, check_scopes as ScopeCheck)
+}
+
+fn check_scopes(
+    State(required): State<RequiredScopes>,
+    req: Request,
+    next: Next,
+) -> Pin<Box<dyn Future<Output = Response> + Send>> {
+    Box::pin(async move {
+        if let Err(e) = grants_of(req.extensions()).and_then(|g| g.require_scopes(&required.0)) {
+            return e.into_response();
+        }
+        next.run(req).await
+    })
+}
+
+/// Scopes required by an [`Authorized`] extractor.
+pub trait Scope {
+    const SCOPES: &'static [&'static str];
+}
+
+/// Extracts the `Grants` of the request, rejecting it with `403` unless they include every
+/// scope of `S`.
+///
+/// ```
+/// use service_utils_rs::services::http::middleware::scope_mw::{Authorized, Scope};
+///
+/// struct OrdersWrite;
+///
+/// impl Scope for OrdersWrite {
+///     const SCOPES: &'static [&'static str] = &["orders:write"];
+/// }
+///
+/// async fn create_order(_: Authorized<OrdersWrite>) {}
+/// ```
+#[derive(Debug, Clone)]
+pub struct Authorized<S> {
+    pub grants: Grants,
+    _scope: PhantomData<fn() -> S>,
+}
+
+impl<S, St> FromRequestParts<St> for Authorized<S>
+where
+    S: Scope,
+    St: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &St) -> Result<Self> {
+        let grants = grants_of(&parts.extensions)?;
+        grants.require_scopes(S::SCOPES)?;
+        Ok(Self {
+            grants: grants.clone(),
+            _scope: PhantomData,
+        })
+    }
+}
+
+fn grants_of(extensions: &axum::http::Extensions) -> Result<&Grants> {
+    extensions
+        .get::<Grants>()
+        .ok_or_else(|| Error::AuthError("missing credentials".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Extension, Router, body::Body, http::StatusCode, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    struct OrdersWrite;
+
+    impl Scope for OrdersWrite {
+        const SCOPES: &'static [&'static str] = &["orders:write"];
+    }
+
+    async fn status(router: Router, uri: &str, grants: Option<Grants>) -> StatusCode {
+        let router = match grants {
+            Some(grants) => router.layer(Extension(grants)),
+            None => router,
+        };
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_scopes() {
+        let router = Router::new()
+            .route("/layer", get(|| async {}))
+            .route_layer(require_scopes(["orders:write"]))
+            .route("/extractor", get(|_: Authorized<OrdersWrite>| async {}));
+        let writer = Grants::new().with_scopes(["orders:read", "orders:write"]);
+        let reader = Grants::new().with_scopes(["orders:read"]);
+
+        for uri in ["/layer", "/extractor"] {
+            assert_eq!(
+                status(router.clone(), uri, Some(writer.clone())).await,
+                StatusCode::OK
+            );
+            assert_eq!(
+                status(router.clone(), uri, Some(reader.clone())).await,
+                StatusCode::FORBIDDEN
+            );
+            assert_eq!(
+                status(router.clone(), uri, None).await,
+                StatusCode::UNAUTHORIZED
+            );
+        }
+    }
+}
@@ -1,4 +1,7 @@
 pub mod http_server;
+#[cfg(feature = "jwt")]
+pub mod jwks;
 pub mod middleware;
 pub mod response;
+pub use http_server::HttpCfg;
 pub use response::{CommonError, CommonResponse, IntoCommonResponse};
@@ -1,9 +1,17 @@
 use core::str;
 
-use axum::{Json, http::StatusCode};
+use axum::{
+    Json,
+    extract::Request,
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
 use serde::Serialize;
 use utoipa::ToSchema;
 
+use crate::error::Error;
+
 #[derive(Debug, Serialize, ToSchema, Default, Clone)]
 pub struct Empty;
 
@@ -61,7 +69,7 @@ where
     }
 }
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct CommonError {
     pub code: i16,
     pub message: String,
@@ -73,6 +81,98 @@ impl CommonError {
     }
 }
 
+impl CommonError {
+    /// Builds the envelope for `err` with its full message and source chain.
+    pub fn detailed(err: &Error) -> Self {
+        let mut message = err.to_string();
+        for cause in err.source_chain() {
+            message.push_str(": ");
+            message.push_str(&cause);
+        }
+        CommonError {
+            code: common_code(err),
+            message,
+        }
+    }
+}
+
+impl From<&Error> for CommonError {
+    fn from(err: &Error) -> Self {
+        CommonError {
+            code: common_code(err),
+            message: err.user_message(),
+        }
+    }
+}
+
+/// Returns the code of `err` for the envelope, saturating codes that do not fit in an `i16`,
+/// which only `Error::ErrorCode` can carry.
+fn common_code(err: &Error) -> i16 {
+    i16::try_from(err.code()).unwrap_or(i16::MAX)
+}
+
+/// [`CommonError::detailed`] of an error response, written out by [`expose_error_details`].
+#[derive(Clone)]
+struct ErrorDetails(CommonError);
+
+/// Middleware replacing the user-safe message of error responses built from an `Error` with
+/// the full error and its causes, e.g.
+/// `router.layer(middleware::from_fn(expose_error_details))`.
+///
+/// Enable only for development, as details may leak internals. Errors already converted into
+/// `(StatusCode, Json<CommonError>)`, such as those of [`ResponseResult`] handlers, keep their
+/// message.
+pub async fn expose_error_details(req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    match response.extensions_mut().remove::<ErrorDetails>() {
+        Some(ErrorDetails(details)) => {
+            let (parts, _) = response.into_parts();
+            (parts, details.to_json()).into_response()
+        }
+        None => response,
+    }
+}
+
+impl From<Error> for (StatusCode, Json<CommonError>) {
+    fn from(err: Error) -> Self {
+        let status =
+            StatusCode::from_u16(err.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, CommonError::from(&err).to_json())
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let details = ErrorDetails(CommonError::detailed(&self));
+        let challenge = match &self {
+            #[cfg(feature = "jwt")]
+            Error::TokenError(error) => Some(error.www_authenticate()),
+            Error::PermissionDenied(message) => Some(format!(
+                "Bearer error=\"insufficient_scope\", error_description=\"{}\"",
+                quote(message)
+            )),
+            _ => None,
+        };
+        let challenge = challenge.map(|challenge| [(header::WWW_AUTHENTICATE, challenge)]);
+        let mut response =
+            (challenge, <(StatusCode, Json<CommonError>)>::from(self)).into_response();
+        response.extensions_mut().insert(details);
+        response
+    }
+}
+
+/// Escapes `value` for a quoted header parameter, dropping characters that headers cannot carry.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len());
+    for c in value.chars().filter(|c| matches!(c, ' ' ..= '~')) {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
 impl Into<CommonError> for (i16, &str) {
     fn into(self) -> CommonError {
         CommonError {
@@ -0,0 +1,91 @@
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind};
+use thiserror::Error;
+
+/// Reason a token was rejected.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenError {
+    #[error("token expired")]
+    Expired,
+
+    #[error("token not yet valid")]
+    NotYetValid,
+
+    #[error("invalid token signature")]
+    InvalidSignature,
+
+    #[error("invalid token audience")]
+    InvalidAudience,
+
+    #[error("invalid token issuer")]
+    InvalidIssuer,
+
+    #[error("malformed token")]
+    Malformed,
+
+    #[error("token revoked")]
+    Revoked,
+}
+
+impl TokenError {
+    /// Classifies a `jsonwebtoken` error, returning `None` for errors that are not caused by
+    /// the token itself, such as an unusable key.
+    pub fn from_jwt(err: &JwtError) -> Option<Self> {
+        let error = match err.kind() {
+            ErrorKind::ExpiredSignature => TokenError::Expired,
+            ErrorKind::ImmatureSignature => TokenError::NotYetValid,
+            ErrorKind::InvalidSignature | ErrorKind::InvalidAlgorithm => {
+                TokenError::InvalidSignature
+            }
+            ErrorKind::InvalidAudience => TokenError::InvalidAudience,
+            ErrorKind::InvalidIssuer => TokenError::InvalidIssuer,
+            ErrorKind::InvalidToken
+            | ErrorKind::InvalidAlgorithmName
+            | ErrorKind::MissingAlgorithm
+            | ErrorKind::MissingRequiredClaim(_)
+            | ErrorKind::InvalidSubject
+            | ErrorKind::Base64(_)
+            | ErrorKind::Json(_)
+            | ErrorKind::Utf8(_) => TokenError::Malformed,
+            _ => return None,
+        };
+        Some(error)
+    }
+
+    /// Returns the `WWW-Authenticate` challenge for a bearer token rejected with this error,
+    /// as defined by RFC 6750.
+    pub fn www_authenticate(&self) -> String {
+        format!(
+            "Bearer error=\"invalid_token\", error_description=\"{}\"",
+            self
+        )
+    }
+}
+
+/// Converts a `jsonwebtoken` error into a [`TokenError`] when the token is at fault.
+pub(crate) fn classify(err: JwtError) -> crate::error::Error {
+    match TokenError::from_jwt(&err) {
+        Some(error) => error.into(),
+        None => err.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            TokenError::from_jwt(&ErrorKind::ExpiredSignature.into()),
+            Some(TokenError::Expired)
+        );
+        assert_eq!(
+            TokenError::from_jwt(&ErrorKind::InvalidRsaKey("bad".to_string()).into()),
+            None
+        );
+        assert_eq!(
+            TokenError::Expired.www_authenticate(),
+            "Bearer error=\"invalid_token\", error_description=\"token expired\""
+        );
+    }
+}
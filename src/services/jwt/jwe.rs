@@ -0,0 +1,154 @@
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use serde::{Deserialize, Serialize};
+
+use super::TokenError;
+use crate::error::{Error, Result};
+
+const TAG_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JweHeader {
+    alg: String,
+    enc: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cty: Option<String>,
+}
+
+/// Content encryption key wrapping signed tokens into JWEs, using direct encryption (`dir`)
+/// with AES-256-GCM (`A256GCM`).
+///
+/// Encrypted tokens are nested JWTs: the signed token is the plaintext, so decrypting one
+/// still has to be followed by the usual signature and claim checks.
+#[derive(Clone)]
+pub struct JweKey {
+    cipher: Aes256Gcm,
+}
+
+impl JweKey {
+    /// Creates a key from 32 raw bytes.
+    pub fn new(key: &[u8]) -> Result<Self> {
+        if key.len() != 32 {
+            return Err(invalid_key());
+        }
+        Ok(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        })
+    }
+
+    /// Creates a key from the base64 encoding of 32 bytes, e.g. the output of
+    /// `openssl rand -base64 32`.
+    pub fn from_base64(key: &str) -> Result<Self> {
+        let key = STANDARD.decode(key.trim()).map_err(|_| invalid_key())?;
+        Self::new(&key)
+    }
+
+    /// Encrypts a signed token into a JWE in compact serialization.
+    pub fn encrypt(&self, token: &str) -> Result<String> {
+        let header = JweHeader {
+            alg: "dir".to_string(),
+            enc: "A256GCM".to_string(),
+            cty: Some("JWT".to_string()),
+        };
+        let header = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?);
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let mut ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: token.as_bytes(),
+                    aad: header.as_bytes(),
+                },
+            )
+            .map_err(|_| Error::SystemError("failed to encrypt token".to_string()))?;
+        let tag = ciphertext.split_off(ciphertext.len() - TAG_LEN);
+        Ok(format!(
+            "{}..{}.{}.{}",
+            header,
+            URL_SAFE_NO_PAD.encode(nonce),
+            URL_SAFE_NO_PAD.encode(ciphertext),
+            URL_SAFE_NO_PAD.encode(tag)
+        ))
+    }
+
+    /// Decrypts a JWE in compact serialization, returning the signed token it wraps.
+    ///
+    /// Fails with [`TokenError::Malformed`] if the token cannot be parsed or uses another
+    /// algorithm, and with [`TokenError::InvalidSignature`] if it was not encrypted with this
+    /// key or was tampered with.
+    pub fn decrypt(&self, token: &str) -> Result<String> {
+        let malformed = || Error::from(TokenError::Malformed);
+        let parts: Vec<&str> = token.split('.').collect();
+        let [header, encrypted_key, nonce, ciphertext, tag] = parts[..] else {
+            return Err(malformed());
+        };
+        let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|_| malformed());
+        let parsed: JweHeader =
+            serde_json::from_slice(&decode(header)?).map_err(|_| malformed())?;
+        if parsed.alg != "dir" || parsed.enc != "A256GCM" || !encrypted_key.is_empty() {
+            return Err(malformed());
+        }
+        let nonce = decode(nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(malformed());
+        }
+        let mut msg = decode(ciphertext)?;
+        msg.extend(decode(tag)?);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &msg,
+                    aad: header.as_bytes(),
+                },
+            )
+            .map_err(|_| Error::from(TokenError::InvalidSignature))?;
+        String::from_utf8(plaintext).map_err(|_| malformed())
+    }
+}
+
+/// Returns `true` if `token` looks like a JWE in compact serialization rather than a JWS.
+pub fn is_jwe(token: &str) -> bool {
+    token.split('.').count() == 5
+}
+
+fn invalid_key() -> Error {
+    Error::ConfigError(config::ConfigError::Message(
+        "jwt.encryption_key must be 32 base64-encoded bytes".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let key = JweKey::new(&[7; 32]).unwrap();
+        let token = key.encrypt("a.b.c").unwrap();
+        assert!(is_jwe(&token));
+        assert!(!token.contains("a.b.c"));
+        assert_eq!(key.decrypt(&token).unwrap(), "a.b.c");
+
+        let other = JweKey::new(&[8; 32]).unwrap();
+        assert!(matches!(
+            other.decrypt(&token),
+            Err(Error::TokenError(TokenError::InvalidSignature))
+        ));
+        assert!(matches!(
+            key.decrypt("a.b.c"),
+            Err(Error::TokenError(TokenError::Malformed))
+        ));
+        assert!(JweKey::from_base64("c2hvcnQ=").is_err());
+        assert!(JweKey::from_base64(&STANDARD.encode([1; 32])).is_ok());
+    }
+}
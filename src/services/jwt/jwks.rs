@@ -0,0 +1,363 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{
+    Algorithm, DecodingKey,
+    errors::ErrorKind,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters, Jwk,
+        JwkSet, KeyAlgorithm, OctetKeyPairParameters, PublicKeyUse, RSAKeyParameters,
+    },
+};
+use tokio::task::JoinHandle;
+
+use super::keys::{KeyFamily, load_decoding_key, public_key_bits, read_tlv};
+use crate::error::{Error, Result, ResultExt};
+
+/// A key accepted when verifying tokens.
+pub struct VerifyingKey {
+    pub algorithm: Algorithm,
+    key: DecodingKey,
+    /// Public form of the key, `None` for HMAC keys.
+    jwk: Option<Jwk>,
+}
+
+impl VerifyingKey {
+    /// Creates a key verifying HMAC tokens. HMAC keys are never published.
+    pub fn hmac(algorithm: Algorithm, secret: &[u8]) -> Self {
+        Self {
+            algorithm,
+            key: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    /// Loads a PEM or DER public key and derives its JWK.
+    pub fn from_public_key_file(kid: &str, algorithm: Algorithm, path: &str) -> Result<Self> {
+        let key = load_decoding_key(algorithm, path)?;
+        let bits = public_key_bits(path)?;
+        let jwk = public_jwk(kid, algorithm, &bits)?;
+        Ok(Self {
+            algorithm,
+            key,
+            jwk: Some(jwk),
+        })
+    }
+
+    /// Creates a key from a JWK, taking the algorithm from `alg` or the key type.
+    ///
+    /// Symmetric (`oct`) keys are rejected, since a key set must never carry secrets.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        let algorithm = jwk_algorithm(jwk).ok_or_else(invalid_key)?;
+        if KeyFamily::of(algorithm) == KeyFamily::Hmac {
+            return Err(invalid_key());
+        }
+        Ok(Self {
+            algorithm,
+            key: DecodingKey::from_jwk(jwk)?,
+            jwk: Some(jwk.clone()),
+        })
+    }
+
+    /// Returns the decoding key.
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.key
+    }
+}
+
+/// Verifying keys identified by `kid`.
+///
+/// Keys are kept in two groups: local keys, added by the service itself and published by
+/// [`KeySet::to_jwks`], and remote keys, loaded from a JWKS and replaced wholesale on each
+/// refresh. Local keys take precedence on a `kid` clash.
+#[derive(Default)]
+pub struct KeySet {
+    local: RwLock<HashMap<String, Arc<VerifyingKey>>>,
+    remote: RwLock<HashMap<String, Arc<VerifyingKey>>>,
+}
+
+impl KeySet {
+    /// Creates an empty key set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces a local key.
+    pub fn insert(&self, kid: &str, key: VerifyingKey) {
+        self.local
+            .write()
+            .unwrap()
+            .insert(kid.to_string(), Arc::new(key));
+    }
+
+    /// Removes a local key, returning `true` if it was present.
+    pub fn remove(&self, kid: &str) -> bool {
+        self.local.write().unwrap().remove(kid).is_some()
+    }
+
+    /// Returns the key with the given id, if any.
+    pub fn get(&self, kid: &str) -> Option<Arc<VerifyingKey>> {
+        if let Some(key) = self.local.read().unwrap().get(kid) {
+            return Some(key.clone());
+        }
+        self.remote.read().unwrap().get(kid).cloned()
+    }
+
+    /// Returns the ids of every key, sorted.
+    pub fn kids(&self) -> Vec<String> {
+        let mut kids: Vec<String> = self.local.read().unwrap().keys().cloned().collect();
+        kids.extend(self.remote.read().unwrap().keys().cloned());
+        kids.sort();
+        kids.dedup();
+        kids
+    }
+
+    /// Returns the public local keys as a JWKS document. HMAC keys are never included.
+    pub fn to_jwks(&self) -> JwkSet {
+        let local = self.local.read().unwrap();
+        let mut keys: Vec<Jwk> = local.values().filter_map(|key| key.jwk.clone()).collect();
+        keys.sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+        JwkSet { keys }
+    }
+
+    /// Replaces the remote keys with the keys of `jwks`.
+    ///
+    /// Keys without `kid` or of an unsupported type are skipped. Returns the number of keys
+    /// loaded.
+    pub fn replace_remote(&self, jwks: &JwkSet) -> usize {
+        let mut keys = HashMap::new();
+        for jwk in &jwks.keys {
+            let Some(kid) = &jwk.common.key_id else {
+                continue;
+            };
+            match VerifyingKey::from_jwk(jwk) {
+                Ok(key) => {
+                    keys.insert(kid.clone(), Arc::new(key));
+                }
+                Err(e) => eprintln!("Skipping JWKS key {}: {}", kid, e),
+            }
+        }
+        let count = keys.len();
+        *self.remote.write().unwrap() = keys;
+        count
+    }
+
+    /// Loads `source` and replaces the remote keys with its keys.
+    pub async fn refresh(&self, source: &JwksSource) -> Result<()> {
+        let jwks = source.load().await?;
+        self.replace_remote(&jwks);
+        Ok(())
+    }
+
+    /// Refreshes the remote keys from `source` now and then every `interval`.
+    ///
+    /// A failed refresh keeps the previous keys and is logged. Must be called from within a
+    /// Tokio runtime.
+    pub fn spawn_refresh(
+        self: &Arc<Self>,
+        source: JwksSource,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        let key_set = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = key_set.refresh(&source).await {
+                    eprintln!("Failed to refresh JWKS, keeping previous keys: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// Location of a JWKS document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwksSource {
+    File(String),
+    #[cfg(feature = "request")]
+    Url(String),
+}
+
+impl JwksSource {
+    /// Reads and parses the JWKS document.
+    pub async fn load(&self) -> Result<JwkSet> {
+        match self {
+            JwksSource::File(path) => {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || read_jwks_file(&path))
+                    .await
+                    .map_err(|e| Error::SystemError(e.to_string()))?
+            }
+            #[cfg(feature = "request")]
+            JwksSource::Url(url) => {
+                let response = crate::utils::Request::new().get(url, None, None).await?;
+                if !response.status().is_success() {
+                    return Err(Error::ErrorMessage(format!(
+                        "Unexpected status fetching JWKS {}: {}",
+                        url,
+                        response.status()
+                    )));
+                }
+                response
+                    .json()
+                    .await
+                    .with_context(|| format!("parsing JWKS {}", url))
+            }
+        }
+    }
+}
+
+/// Reads and parses a JWKS file.
+pub fn read_jwks_file(path: &str) -> Result<JwkSet> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading JWKS {}", path))?;
+    serde_json::from_slice(&bytes).with_context(|| format!("parsing JWKS {}", path))
+}
+
+/// Builds the public JWK of `bits`, as returned by [`public_key_bits`].
+fn public_jwk(kid: &str, algorithm: Algorithm, bits: &[u8]) -> Result<Jwk> {
+    let parameters = match KeyFamily::of(algorithm) {
+        KeyFamily::Hmac => return Err(invalid_key()),
+        KeyFamily::Rsa => {
+            let (n, e) = rsa_components(bits).ok_or_else(invalid_key)?;
+            AlgorithmParameters::RSA(RSAKeyParameters {
+                n: URL_SAFE_NO_PAD.encode(n),
+                e: URL_SAFE_NO_PAD.encode(e),
+                ..Default::default()
+            })
+        }
+        KeyFamily::Ec => {
+            let curve = match algorithm {
+                Algorithm::ES384 => EllipticCurve::P384,
+                _ => EllipticCurve::P256,
+            };
+            let (x, y) = match bits {
+                [4, point @ ..] if point.len() % 2 == 0 => point.split_at(point.len() / 2),
+                _ => return Err(invalid_key()),
+            };
+            AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                curve,
+                x: URL_SAFE_NO_PAD.encode(x),
+                y: URL_SAFE_NO_PAD.encode(y),
+                ..Default::default()
+            })
+        }
+        KeyFamily::Ed => AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(bits),
+            ..Default::default()
+        }),
+    };
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: KeyAlgorithm::from_str(&format!("{:?}", algorithm)).ok(),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+/// Returns the modulus and exponent of a DER PKCS#1 RSA public key, without leading zeros.
+fn rsa_components(der: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (_, sequence, _) = read_tlv(der)?;
+    let (_, n, rest) = read_tlv(sequence)?;
+    let (_, e, _) = read_tlv(rest)?;
+    fn strip(bytes: &[u8]) -> &[u8] {
+        let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+        &bytes[start ..]
+    }
+    Some((strip(n), strip(e)))
+}
+
+fn jwk_algorithm(jwk: &Jwk) -> Option<Algorithm> {
+    if let Some(algorithm) = jwk.common.key_algorithm {
+        return Algorithm::from_str(&algorithm.to_string()).ok();
+    }
+    match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => Some(Algorithm::RS256),
+        AlgorithmParameters::EllipticCurve(params) => match params.curve {
+            EllipticCurve::P256 => Some(Algorithm::ES256),
+            EllipticCurve::P384 => Some(Algorithm::ES384),
+            _ => None,
+        },
+        AlgorithmParameters::OctetKeyPair(_) => Some(Algorithm::EdDSA),
+        AlgorithmParameters::OctetKey(_) => None,
+    }
+}
+
+fn invalid_key() -> Error {
+    Error::JwtError(ErrorKind::InvalidKeyFormat.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_public_keys() {
+        let key_set = KeySet::new();
+        for (kid, algorithm, path) in [
+            ("rsa", Algorithm::RS256, "tests/keys/rsa_public.pem"),
+            ("ec", Algorithm::ES256, "tests/keys/ec_public.der"),
+            ("ed", Algorithm::EdDSA, "tests/keys/ed_public.pem"),
+        ] {
+            key_set.insert(
+                kid,
+                VerifyingKey::from_public_key_file(kid, algorithm, path).unwrap(),
+            );
+        }
+        key_set.insert("hmac", VerifyingKey::hmac(Algorithm::HS256, b"secret"));
+
+        let jwks = key_set.to_jwks();
+        let kids: Vec<&str> = jwks
+            .keys
+            .iter()
+            .map(|jwk| jwk.common.key_id.as_deref().unwrap())
+            .collect();
+        assert_eq!(kids, ["ec", "ed", "rsa"]);
+        assert_eq!(key_set.kids(), ["ec", "ed", "hmac", "rsa"]);
+
+        let json = serde_json::to_value(&jwks).unwrap();
+        assert_eq!(json["keys"][2]["kty"], "RSA");
+        assert_eq!(json["keys"][2]["e"], "AQAB");
+        assert_eq!(json["keys"][0]["crv"], "P-256");
+        assert_eq!(json["keys"][1]["alg"], "EdDSA");
+    }
+
+    #[tokio::test]
+    async fn test_load_jwks_file() {
+        let exported = KeySet::new();
+        exported.insert(
+            "ec-1",
+            VerifyingKey::from_public_key_file(
+                "ec-1",
+                Algorithm::ES256,
+                "tests/keys/ec_public.pem",
+            )
+            .unwrap(),
+        );
+        let mut jwks = exported.to_jwks();
+        jwks.keys
+            .push(serde_json::from_str(r#"{"kty":"oct","kid":"s","k":"c2VjcmV0"}"#).unwrap());
+        let path = std::env::temp_dir().join(format!("sur_jwks_{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_vec(&jwks).unwrap()).unwrap();
+
+        let key_set = KeySet::new();
+        let source = JwksSource::File(path.to_str().unwrap().to_string());
+        key_set.refresh(&source).await.unwrap();
+        assert_eq!(key_set.kids(), ["ec-1"]);
+        assert_eq!(key_set.get("ec-1").unwrap().algorithm, Algorithm::ES256);
+        assert!(key_set.to_jwks().keys.is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(key_set.refresh(&source).await.is_err());
+        assert!(key_set.get("ec-1").is_some());
+    }
+}
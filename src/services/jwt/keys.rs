@@ -0,0 +1,200 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, errors::ErrorKind};
+
+use crate::error::{Error, Result, ResultExt};
+
+const DER_SEQUENCE: u8 = 0x30;
+const DER_INTEGER: u8 = 0x02;
+const DER_BIT_STRING: u8 = 0x03;
+const DER_OCTET_STRING: u8 = 0x04;
+
+/// Kind of key an algorithm works with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyFamily {
+    Hmac,
+    Rsa,
+    Ec,
+    Ed,
+}
+
+impl KeyFamily {
+    /// Returns the key family of `algorithm`.
+    pub fn of(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => KeyFamily::Hmac,
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => KeyFamily::Rsa,
+            Algorithm::ES256 | Algorithm::ES384 => KeyFamily::Ec,
+            Algorithm::EdDSA => KeyFamily::Ed,
+        }
+    }
+}
+
+/// Loads a PEM or DER private key for `algorithm`.
+///
+/// DER keys are PKCS#8; RSA keys may also be PKCS#1.
+pub fn load_encoding_key(algorithm: Algorithm, path: &str) -> Result<EncodingKey> {
+    let bytes = read_key(path)?;
+    let key = if is_pem(&bytes) {
+        match KeyFamily::of(algorithm) {
+            KeyFamily::Hmac => EncodingKey::from_secret(&bytes),
+            KeyFamily::Rsa => EncodingKey::from_rsa_pem(&bytes)?,
+            KeyFamily::Ec => EncodingKey::from_ec_pem(&bytes)?,
+            KeyFamily::Ed => EncodingKey::from_ed_pem(&bytes)?,
+        }
+    } else {
+        match KeyFamily::of(algorithm) {
+            KeyFamily::Hmac => EncodingKey::from_secret(&bytes),
+            KeyFamily::Rsa => {
+                EncodingKey::from_rsa_der(pkcs8_private_key(&bytes).unwrap_or(&bytes))
+            }
+            KeyFamily::Ec => EncodingKey::from_ec_der(&bytes),
+            KeyFamily::Ed => EncodingKey::from_ed_der(&bytes),
+        }
+    };
+    Ok(key)
+}
+
+/// Loads a PEM or DER public key for `algorithm`.
+///
+/// DER keys are SubjectPublicKeyInfo; RSA keys may also be PKCS#1.
+pub fn load_decoding_key(algorithm: Algorithm, path: &str) -> Result<DecodingKey> {
+    let bytes = read_key(path)?;
+    let key = if is_pem(&bytes) {
+        match KeyFamily::of(algorithm) {
+            KeyFamily::Hmac => DecodingKey::from_secret(&bytes),
+            KeyFamily::Rsa => DecodingKey::from_rsa_pem(&bytes)?,
+            KeyFamily::Ec => DecodingKey::from_ec_pem(&bytes)?,
+            KeyFamily::Ed => DecodingKey::from_ed_pem(&bytes)?,
+        }
+    } else {
+        let raw = spki_public_key(&bytes).unwrap_or(&bytes);
+        match KeyFamily::of(algorithm) {
+            KeyFamily::Hmac => DecodingKey::from_secret(&bytes),
+            KeyFamily::Rsa => DecodingKey::from_rsa_der(raw),
+            KeyFamily::Ec => DecodingKey::from_ec_der(raw),
+            KeyFamily::Ed => DecodingKey::from_ed_der(raw),
+        }
+    };
+    Ok(key)
+}
+
+/// Reads a PEM or DER public key and returns its key bits: the PKCS#1 `RSAPublicKey` for RSA,
+/// the encoded point for ECDSA and the raw 32 bytes for Ed25519.
+pub fn public_key_bits(path: &str) -> Result<Vec<u8>> {
+    let bytes = read_key(path)?;
+    let (der, pkcs1) = if is_pem(&bytes) {
+        let text = String::from_utf8_lossy(&bytes);
+        let pkcs1 = text.contains("-----BEGIN RSA PUBLIC KEY-----");
+        let body: String = text
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .map(str::trim)
+            .collect();
+        let der = STANDARD
+            .decode(body)
+            .map_err(|_| Error::JwtError(ErrorKind::InvalidKeyFormat.into()))?;
+        (der, pkcs1)
+    } else {
+        (bytes, false)
+    };
+    if pkcs1 {
+        return Ok(der);
+    }
+    Ok(spki_public_key(&der).map(<[u8]>::to_vec).unwrap_or(der))
+}
+
+fn read_key(path: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("reading JWT key {}", path))
+}
+
+fn is_pem(bytes: &[u8]) -> bool {
+    bytes.trim_ascii_start().starts_with(b"-----BEGIN")
+}
+
+/// Returns the key bits of a DER SubjectPublicKeyInfo, or `None` if `der` is not one.
+pub(crate) fn spki_public_key(der: &[u8]) -> Option<&[u8]> {
+    let (tag, spki, _) = read_tlv(der)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    let (tag, _, rest) = read_tlv(spki)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    let (tag, bits, _) = read_tlv(rest)?;
+    match (tag, bits) {
+        (DER_BIT_STRING, [0, key @ ..]) => Some(key),
+        _ => None,
+    }
+}
+
+/// Returns the inner private key of a DER PKCS#8 document, or `None` if `der` is not one.
+fn pkcs8_private_key(der: &[u8]) -> Option<&[u8]> {
+    let (tag, info, _) = read_tlv(der)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    let (tag, _, rest) = read_tlv(info)?;
+    if tag != DER_INTEGER {
+        return None;
+    }
+    let (tag, _, rest) = read_tlv(rest)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    let (tag, key, _) = read_tlv(rest)?;
+    (tag == DER_OCTET_STRING).then_some(key)
+}
+
+/// Splits the first DER element off `input`, returning its tag, content and the remaining bytes.
+pub(crate) fn read_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[.. count]
+            .iter()
+            .fold(0usize, |len, &b| (len << 8) | b as usize);
+        (len, &rest[count ..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[.. len], &rest[len ..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_der_containers() {
+        let spki = std::fs::read("tests/keys/ed_public.der").unwrap();
+        assert_eq!(spki_public_key(&spki).unwrap().len(), 32);
+
+        let pkcs8 = std::fs::read("tests/keys/rsa_private.der").unwrap();
+        let pkcs1 = pkcs8_private_key(&pkcs8).unwrap();
+        assert_eq!(read_tlv(pkcs1).unwrap().0, DER_SEQUENCE);
+        assert!(spki_public_key(pkcs1).is_none());
+
+        assert!(read_tlv(&[DER_SEQUENCE, 0x82, 0x01]).is_none());
+    }
+
+    #[test]
+    fn test_missing_key_file() {
+        let err = load_decoding_key(Algorithm::RS256, "tests/keys/missing.pem")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("tests/keys/missing.pem"));
+    }
+}
@@ -0,0 +1,2038 @@
+pub mod error;
+#[cfg(feature = "jwe")]
+pub mod jwe;
+pub mod jwks;
+pub mod keys;
+pub mod refresh;
+pub mod revocation;
+pub mod signed_url;
+
+use std::{borrow::Cow, sync::Arc};
+
+use chrono::{Duration, Utc};
+pub use error::TokenError;
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation, decode, decode_header,
+    encode, jwk::JwkSet,
+};
+use jwks::{JwksSource, KeySet, VerifyingKey};
+use keys::{KeyFamily, load_decoding_key, load_encoding_key};
+use refresh::RefreshTokenStore;
+use revocation::RevocationStore;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use signed_url::ConsumedTokenStore;
+use tokio::task::JoinHandle;
+
+use crate::{
+    error::{Error, Result},
+    services::Grants,
+    utils::{
+        ConfigDuration, Secret, Validate, ValidationErrors,
+        config_schema::{ConfigField, ConfigSchema, FieldKind},
+    },
+};
+
+/// Struct representing the JWT configuration parameters.
+///
+/// Access tokens are signed with `algorithm`: `HS*` algorithms use `access_secret`, while `RS*`,
+/// `PS*`, `ES*` and `EdDSA` use the PEM or DER keys at `private_key_path` and
+/// `public_key_path`. Without a private key the instance is verify-only. Refresh tokens are
+/// always HMAC-signed with `refresh_secret`, and are disabled when it is empty, as are signed
+/// URLs.
+///
+/// With a `kid`, issued access tokens name their key, and are verified against the key set:
+/// the signing key, `verify_keys` and the keys of the JWKS at `jwks_path` or `jwks_url`. To
+/// rotate keys, move the current key to `verify_keys` and configure a new one with a new `kid`.
+///
+/// With the `jwe` feature and an `encryption_key`, issued tokens are also encrypted (`dir` +
+/// `A256GCM`) so clients cannot read their claims. Signed tokens that are not encrypted, such
+/// as those of other issuers in the key set, are still accepted.
+#[derive(Debug, Default, Deserialize)]
+pub struct JwtCfg {
+    /// Algorithm of access tokens, `HS256` by default.
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    #[serde(default)]
+    pub access_secret: Secret<String>,
+    #[serde(default)]
+    pub refresh_secret: Secret<String>,
+    /// Private key signing access tokens with an asymmetric algorithm.
+    #[serde(default)]
+    pub private_key_path: Option<String>,
+    /// Public key verifying access tokens with an asymmetric algorithm.
+    #[serde(default)]
+    pub public_key_path: Option<String>,
+    /// Key id written to the header of access tokens.
+    #[serde(default)]
+    pub kid: Option<String>,
+    /// Other keys accepted when verifying access tokens, e.g. keys being rotated out.
+    #[serde(default)]
+    pub verify_keys: Vec<JwtKeyCfg>,
+    /// JWKS file whose keys are accepted when verifying access tokens.
+    #[serde(default)]
+    pub jwks_path: Option<String>,
+    /// JWKS URL whose keys are accepted when verifying access tokens.
+    #[serde(default)]
+    pub jwks_url: Option<String>,
+    /// Interval between JWKS refreshes, 5 minutes by default.
+    #[serde(default)]
+    pub jwks_refresh_interval: Option<ConfigDuration>,
+    /// Issuer (iss) written to and required in tokens.
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// Audience (aud) written to tokens, and accepted in them.
+    pub audience: String,
+    /// Other audiences accepted in tokens.
+    #[serde(default)]
+    pub accepted_audiences: Vec<String>,
+    /// Clock skew tolerated when checking `exp` and `nbf`, 60 seconds by default.
+    #[serde(default)]
+    pub leeway: Option<ConfigDuration>,
+    /// Lifetime of access tokens, e.g. `"3h"` or `10800` (seconds).
+    pub access_token_duration: ConfigDuration,
+    /// Lifetime of refresh tokens, e.g. `"7d"` or `604800` (seconds).
+    pub refresh_token_duration: ConfigDuration,
+    /// Lifetime of partial tokens, 5 minutes by default.
+    #[serde(default)]
+    pub partial_token_duration: Option<ConfigDuration>,
+    pub access_key_validate_exp: bool,
+    pub refresh_key_validate_exp: bool,
+    /// Base64 of the 32-byte key encrypting issued tokens.
+    #[cfg(feature = "jwe")]
+    #[serde(default)]
+    pub encryption_key: Option<Secret<String>>,
+}
+
+/// Struct representing an additional JWT verification key.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JwtKeyCfg {
+    pub kid: String,
+    /// Algorithm of the key, `HS256` by default.
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    /// HMAC secret, with `HS*` algorithms.
+    #[serde(default)]
+    pub secret: Secret<String>,
+    /// PEM or DER public key, with asymmetric algorithms.
+    #[serde(default)]
+    pub public_key_path: Option<String>,
+}
+
+/// Keys of a `verify_keys` entry.
+const KEY_FIELDS: &[ConfigField] = &[
+    ConfigField::required("kid", FieldKind::String, "Key id.", "\"2023-07\""),
+    ConfigField::optional(
+        "algorithm",
+        FieldKind::String,
+        "Algorithm of the key.",
+        "\"HS256\"",
+    ),
+    ConfigField::optional(
+        "secret",
+        FieldKind::Secret,
+        "HMAC secret, with HS* algorithms.",
+        "\"env://JWT_OLD_ACCESS_SECRET\"",
+    ),
+    ConfigField::optional(
+        "public_key_path",
+        FieldKind::String,
+        "PEM or DER public key, with asymmetric algorithms.",
+        "\"/etc/jwt/old_public.pem\"",
+    ),
+];
+
+impl JwtKeyCfg {
+    /// Loads the key.
+    pub fn load(&self) -> Result<VerifyingKey> {
+        match KeyFamily::of(self.algorithm) {
+            KeyFamily::Hmac => {
+                let secret = self.secret.expose_secret().as_bytes();
+                if secret.is_empty() {
+                    return Err(missing_key("verify_keys.secret", self.algorithm));
+                }
+                Ok(VerifyingKey::hmac(self.algorithm, secret))
+            }
+            _ => {
+                let path = self
+                    .public_key_path
+                    .as_deref()
+                    .ok_or_else(|| missing_key("verify_keys.public_key_path", self.algorithm))?;
+                VerifyingKey::from_public_key_file(&self.kid, self.algorithm, path)
+            }
+        }
+    }
+}
+
+impl Validate for JwtKeyCfg {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        errors.ensure(!self.kid.is_empty(), "kid", "must not be empty");
+        if KeyFamily::of(self.algorithm) == KeyFamily::Hmac {
+            errors.ensure(
+                !self.secret.expose_secret().is_empty(),
+                "secret",
+                "must not be empty",
+            );
+        } else {
+            errors.ensure(
+                self.public_key_path.is_some(),
+                "public_key_path",
+                format!("is required for {:?}", self.algorithm),
+            );
+        }
+    }
+}
+
+impl JwtCfg {
+    /// Returns `true` if access tokens may be verified with keys other than the signing key.
+    pub fn has_key_set(&self) -> bool {
+        !self.verify_keys.is_empty() || self.jwks_path.is_some() || self.jwks_url.is_some()
+    }
+
+    /// Returns `true` if the configuration can sign access tokens.
+    pub fn can_sign(&self) -> bool {
+        match KeyFamily::of(self.algorithm) {
+            KeyFamily::Hmac => !self.access_secret.expose_secret().is_empty(),
+            _ => self.private_key_path.is_some(),
+        }
+    }
+}
+
+impl Validate for JwtCfg {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        if KeyFamily::of(self.algorithm) == KeyFamily::Hmac {
+            errors.ensure(
+                !self.access_secret.expose_secret().is_empty(),
+                "access_secret",
+                "must not be empty",
+            );
+        } else {
+            errors.ensure(
+                self.public_key_path.is_some() || self.has_key_set(),
+                "public_key_path",
+                format!("is required for {:?}", self.algorithm),
+            );
+        }
+        for (i, key) in self.verify_keys.iter().enumerate() {
+            errors.nested(&format!("verify_keys[{}]", i), key);
+        }
+        errors.ensure(
+            self.jwks_path.is_none() || self.jwks_url.is_none(),
+            "jwks_url",
+            "must not be set together with jwks_path",
+        );
+        errors.ensure(
+            !self.can_sign() || !self.refresh_secret.expose_secret().is_empty(),
+            "refresh_secret",
+            "must not be empty",
+        );
+        errors.ensure(
+            self.issuer.as_ref().is_none_or(|issuer| !issuer.is_empty()),
+            "issuer",
+            "must not be empty",
+        );
+        errors.ensure(!self.audience.is_empty(), "audience", "must not be empty");
+        for (i, audience) in self.accepted_audiences.iter().enumerate() {
+            errors.ensure(
+                !audience.is_empty(),
+                &format!("accepted_audiences[{}]", i),
+                "must not be empty",
+            );
+        }
+        // Token lifetimes are whole seconds: anything shorter would issue already expired tokens.
+        errors.ensure(
+            self.access_token_duration.as_secs() >= 1,
+            "access_token_duration",
+            "must be at least 1s",
+        );
+        errors.ensure(
+            self.refresh_token_duration.as_secs() >= 1,
+            "refresh_token_duration",
+            "must be at least 1s",
+        );
+        errors.ensure(
+            self.partial_token_duration
+                .is_none_or(|duration| duration.as_secs() >= 1),
+            "partial_token_duration",
+            "must be at least 1s",
+        );
+        #[cfg(feature = "jwe")]
+        if let Some(key) = &self.encryption_key {
+            errors.ensure(
+                jwe::JweKey::from_base64(key.expose_secret()).is_ok(),
+                "encryption_key",
+                "must be 32 base64-encoded bytes",
+            );
+        }
+    }
+}
+
+impl ConfigSchema for JwtCfg {
+    fn description() -> &'static str {
+        "JWT issuing and validation."
+    }
+
+    fn fields() -> Vec<ConfigField> {
+        #[allow(unused_mut)]
+        let mut fields = vec![
+            ConfigField::optional(
+                "algorithm",
+                FieldKind::String,
+                "Algorithm of access tokens: HS256, RS256, ES256, EdDSA, ...",
+                "\"HS256\"",
+            ),
+            ConfigField::optional(
+                "access_secret",
+                FieldKind::Secret,
+                "HMAC secret for access tokens, with HS* algorithms.",
+                "\"env://JWT_ACCESS_SECRET\"",
+            )
+            .uncommented(),
+            ConfigField::optional(
+                "refresh_secret",
+                FieldKind::Secret,
+                "HMAC secret for refresh tokens, empty on verify-only instances.",
+                "\"env://JWT_REFRESH_SECRET\"",
+            )
+            .uncommented(),
+            ConfigField::optional(
+                "private_key_path",
+                FieldKind::String,
+                "PEM or DER private key, with asymmetric algorithms. Omit to only verify.",
+                "\"/run/secrets/jwt_private.pem\"",
+            ),
+            ConfigField::optional(
+                "public_key_path",
+                FieldKind::String,
+                "PEM or DER public key, with asymmetric algorithms.",
+                "\"/etc/jwt/public.pem\"",
+            ),
+            ConfigField::optional(
+                "kid",
+                FieldKind::String,
+                "Key id written to the header of access tokens.",
+                "\"2024-01\"",
+            ),
+            ConfigField::optional(
+                "verify_keys",
+                FieldKind::TableList(KEY_FIELDS),
+                "Other keys accepted when verifying access tokens, e.g. keys being rotated out.",
+                "[{ kid = \"2023-07\", secret = \"env://JWT_OLD_ACCESS_SECRET\" }]",
+            ),
+            ConfigField::optional(
+                "jwks_path",
+                FieldKind::String,
+                "JWKS file whose keys are accepted when verifying access tokens.",
+                "\"/etc/jwt/jwks.json\"",
+            ),
+            ConfigField::optional(
+                "jwks_url",
+                FieldKind::String,
+                "JWKS whose keys are accepted when verifying access tokens.",
+                "\"https://auth.example.com/.well-known/jwks.json\"",
+            ),
+            ConfigField::optional(
+                "jwks_refresh_interval",
+                FieldKind::Duration,
+                "Interval between JWKS refreshes.",
+                "\"5m\"",
+            ),
+            ConfigField::optional(
+                "issuer",
+                FieldKind::String,
+                "Issuer (iss) written to and required in tokens.",
+                "\"https://auth.example.com\"",
+            ),
+            ConfigField::required(
+                "audience",
+                FieldKind::String,
+                "Audience (aud) written to and accepted in tokens.",
+                "\"my-service\"",
+            ),
+            ConfigField::optional(
+                "accepted_audiences",
+                FieldKind::StringList,
+                "Other audiences accepted in tokens.",
+                "[\"my-other-service\"]",
+            ),
+            ConfigField::optional(
+                "leeway",
+                FieldKind::Duration,
+                "Clock skew tolerated when checking exp and nbf.",
+                "\"1m\"",
+            ),
+            ConfigField::required(
+                "access_token_duration",
+                FieldKind::Duration,
+                "Lifetime of access tokens.",
+                "\"3h\"",
+            ),
+            ConfigField::required(
+                "refresh_token_duration",
+                FieldKind::Duration,
+                "Lifetime of refresh tokens.",
+                "\"7d\"",
+            ),
+            ConfigField::optional(
+                "partial_token_duration",
+                FieldKind::Duration,
+                "Lifetime of partial tokens, issued until a second factor is verified.",
+                "\"5m\"",
+            ),
+            ConfigField::required(
+                "access_key_validate_exp",
+                FieldKind::Boolean,
+                "Reject expired access tokens.",
+                "true",
+            ),
+            ConfigField::required(
+                "refresh_key_validate_exp",
+                FieldKind::Boolean,
+                "Reject expired refresh tokens.",
+                "true",
+            ),
+        ];
+        #[cfg(feature = "jwe")]
+        fields.push(ConfigField::optional(
+            "encryption_key",
+            FieldKind::Secret,
+            "Base64 of the 32-byte key encrypting issued tokens (JWE dir + A256GCM).",
+            "\"env://JWT_ENCRYPTION_KEY\"",
+        ));
+        fields
+    }
+}
+
+/// Represents the JWT claims.
+///
+/// `C` holds application claims such as a tenant id, flattened into the token next to the
+/// registered ones, whose names it must not reuse.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims<C = ()> {
+    pub aud: String,
+    pub sub: String,
+    pub exp: usize,
+    pub iat: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    /// Time before which the token must be rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    /// Token id, used to revoke and rotate tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Id of the refresh token family, the chain of refresh tokens issued from one login.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fam: Option<String>,
+    /// Space-separated scopes granted to the subject.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Roles granted to the subject.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(flatten)]
+    pub extra: C,
+}
+
+impl Claims {
+    /// Creates a new `Claims` instance.
+    pub fn new(aud: String, sub: String, exp: usize, iat: usize) -> Self {
+        Self::with_extra(aud, sub, exp, iat, ())
+    }
+}
+
+impl<C> Claims<C> {
+    /// Creates a new `Claims` instance carrying application claims.
+    pub fn with_extra(aud: String, sub: String, exp: usize, iat: usize, extra: C) -> Self {
+        Self {
+            aud,
+            sub,
+            exp,
+            iat,
+            iss: None,
+            nbf: None,
+            jti: None,
+            fam: None,
+            scope: None,
+            roles: Vec::new(),
+            extra,
+        }
+    }
+
+    /// Returns the scopes and roles granted to the subject.
+    pub fn grants(&self) -> Grants {
+        Grants {
+            scopes: self
+                .scope
+                .as_deref()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            roles: self.roles.clone(),
+        }
+    }
+
+    /// Sets the scopes and roles granted to the subject.
+    pub fn set_grants(&mut self, grants: &Grants) {
+        self.scope = (!grants.scopes.is_empty()).then(|| grants.scopes.join(" "));
+        self.roles = grants.roles.clone();
+    }
+}
+
+/// Enum representing the type of token: ACCESS or REFRESH.
+enum TokenKind {
+    ACCESS,
+    REFRESH,
+    PARTIAL,
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::ACCESS => f.write_str("access"),
+            TokenKind::REFRESH => f.write_str("refresh"),
+            TokenKind::PARTIAL => f.write_str("partial"),
+        }
+    }
+}
+
+/// Struct representing the JWT configuration and operations.
+#[derive(Clone)]
+pub struct Jwt {
+    header: Header,
+    refresh_header: Header,
+    encoding_access_key: Option<EncodingKey>,
+    encoding_refresh_key: Option<EncodingKey>,
+    decoding_access_key: Option<DecodingKey>,
+    key_set: Arc<KeySet>,
+    jwks_source: Option<JwksSource>,
+    jwks_refresh_interval: std::time::Duration,
+    decoding_refresh_key: Option<DecodingKey>,
+    validation_access_key: Validation,
+    validation_refresh_key: Validation,
+    validation_partial_key: Validation,
+    aud: String,
+    iss: Option<String>,
+    access_token_duration: usize,
+    refresh_token_duration: usize,
+    partial_token_duration: usize,
+    refresh_store: Option<Arc<dyn RefreshTokenStore>>,
+    revocation_store: Option<Arc<dyn RevocationStore>>,
+    url_key: Option<Secret<Vec<u8>>>,
+    consumed_store: Option<Arc<dyn ConsumedTokenStore>>,
+    #[cfg(feature = "jwe")]
+    encryption: Option<jwe::JweKey>,
+}
+
+impl Jwt {
+    /// Creates a new `Jwt` instance from the given configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - A `JwtCfg` struct containing the JWT configuration.
+    ///
+    /// # Returns
+    ///
+    /// * A new `Jwt` instance.
+    ///
+    /// # Panics
+    ///
+    /// Use [`Jwt::try_new`] to handle these errors instead. `new` panics:
+    ///
+    /// * If an asymmetric algorithm has neither a `public_key_path` nor a key set.
+    /// * If `private_key_path`, `public_key_path` or a key of `verify_keys` cannot be loaded.
+    /// * If the file at `jwks_path` cannot be read or parsed.
+    /// * If both `jwks_path` and `jwks_url` are set, or `jwks_url` is set without the `request`
+    ///   feature.
+    /// * If `encryption_key` is not a valid base64 encoded 256-bit key.
+    ///
+    /// An empty `access_secret` or `refresh_secret` does not panic; see [`JwtCfg::validate`].
+    pub fn new(cfg: JwtCfg) -> Self {
+        Self::try_new(cfg).unwrap_or_else(|e| panic!("invalid JWT configuration: {}", e))
+    }
+
+    /// Creates a new `Jwt` instance from the given configuration, loading its keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - A `JwtCfg` struct containing the JWT configuration.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the new `Jwt` instance, or an `Error` if a key is missing or cannot
+    ///   be read.
+    pub fn try_new(cfg: JwtCfg) -> Result<Self> {
+        let algorithm = cfg.algorithm;
+        let mut header = Header::new(algorithm);
+        header.kid = cfg.kid.clone();
+        let refresh_header = Header::default();
+        let key_set = Arc::new(KeySet::new());
+        let (encoding_access_key, decoding_access_key) = match KeyFamily::of(algorithm) {
+            KeyFamily::Hmac => {
+                let access_secret = cfg.access_secret.expose_secret().as_bytes();
+                if let Some(kid) = &cfg.kid {
+                    key_set.insert(kid, VerifyingKey::hmac(algorithm, access_secret));
+                }
+                (
+                    Some(EncodingKey::from_secret(access_secret)),
+                    Some(DecodingKey::from_secret(access_secret)),
+                )
+            }
+            _ => {
+                if cfg.public_key_path.is_none() && !cfg.has_key_set() {
+                    return Err(missing_key("public_key_path", algorithm));
+                }
+                let encoding_key = match &cfg.private_key_path {
+                    Some(path) => Some(load_encoding_key(algorithm, path)?),
+                    None => None,
+                };
+                let decoding_key = match &cfg.public_key_path {
+                    Some(path) => {
+                        if let Some(kid) = &cfg.kid {
+                            let key = VerifyingKey::from_public_key_file(kid, algorithm, path)?;
+                            key_set.insert(kid, key);
+                        }
+                        Some(load_decoding_key(algorithm, path)?)
+                    }
+                    None => None,
+                };
+                (encoding_key, decoding_key)
+            }
+        };
+        for key in &cfg.verify_keys {
+            key_set.insert(&key.kid, key.load()?);
+        }
+        let jwks_source = match (&cfg.jwks_path, &cfg.jwks_url) {
+            (Some(_), Some(_)) => {
+                return Err(Error::ConfigError(config::ConfigError::Message(
+                    "jwt.jwks_path and jwt.jwks_url must not both be set".to_string(),
+                )));
+            }
+            (Some(path), None) => {
+                key_set.replace_remote(&jwks::read_jwks_file(path)?);
+                Some(JwksSource::File(path.clone()))
+            }
+            #[cfg(feature = "request")]
+            (None, Some(url)) => Some(JwksSource::Url(url.clone())),
+            #[cfg(not(feature = "request"))]
+            (None, Some(_)) => {
+                return Err(Error::ConfigError(config::ConfigError::Message(
+                    "jwt.jwks_url requires the request feature".to_string(),
+                )));
+            }
+            (None, None) => None,
+        };
+        let refresh_secret = cfg.refresh_secret.expose_secret().as_bytes();
+        let (encoding_refresh_key, decoding_refresh_key, url_key) = if refresh_secret.is_empty() {
+            (None, None, None)
+        } else {
+            (
+                Some(EncodingKey::from_secret(refresh_secret)),
+                Some(DecodingKey::from_secret(refresh_secret)),
+                Some(Secret::new(signed_url::derive_url_key(refresh_secret))),
+            )
+        };
+        let mut validation_access_key = Validation::new(algorithm);
+        let mut audiences = vec![cfg.audience.as_str()];
+        audiences.extend(cfg.accepted_audiences.iter().map(String::as_str));
+        validation_access_key.set_audience(&audiences);
+        validation_access_key.validate_nbf = true;
+        if let Some(leeway) = cfg.leeway {
+            validation_access_key.leeway = leeway.as_secs();
+        }
+        let mut validation_refresh_key = validation_access_key.clone();
+        validation_refresh_key.algorithms = vec![refresh_header.alg];
+        validation_access_key.validate_exp = cfg.access_key_validate_exp;
+        validation_refresh_key.validate_exp = cfg.refresh_key_validate_exp;
+        validation_refresh_key.required_spec_claims.clear();
+        if let Some(issuer) = &cfg.issuer {
+            for validation in [&mut validation_access_key, &mut validation_refresh_key] {
+                validation.set_issuer(&[issuer]);
+                validation.required_spec_claims.insert("iss".to_string());
+            }
+        }
+        let mut validation_partial_key = validation_access_key.clone();
+        validation_partial_key.set_audience(&[partial_audience(&cfg.audience)]);
+        validation_partial_key.validate_exp = true;
+        #[cfg(feature = "jwe")]
+        let encryption = match &cfg.encryption_key {
+            Some(key) => Some(jwe::JweKey::from_base64(key.expose_secret())?),
+            None => None,
+        };
+        Ok(Self {
+            header,
+            refresh_header,
+            encoding_access_key,
+            encoding_refresh_key,
+            decoding_access_key,
+            decoding_refresh_key,
+            validation_access_key,
+            validation_refresh_key,
+            validation_partial_key,
+            key_set,
+            jwks_source,
+            jwks_refresh_interval: cfg
+                .jwks_refresh_interval
+                .map_or(DEFAULT_JWKS_REFRESH_INTERVAL, |interval| {
+                    interval.as_duration()
+                }),
+            aud: cfg.audience,
+            iss: cfg.issuer,
+            access_token_duration: cfg.access_token_duration.as_secs() as usize,
+            refresh_token_duration: cfg.refresh_token_duration.as_secs() as usize,
+            partial_token_duration: cfg
+                .partial_token_duration
+                .map_or(DEFAULT_PARTIAL_TOKEN_DURATION, |duration| {
+                    duration.as_secs() as usize
+                }),
+            refresh_store: None,
+            revocation_store: None,
+            url_key,
+            consumed_store: None,
+            #[cfg(feature = "jwe")]
+            encryption,
+        })
+    }
+
+    /// Enables refresh token rotation, tracking rotated tokens in `store`.
+    ///
+    /// See [`Jwt::rotate_refresh_token`].
+    pub fn with_refresh_store(mut self, store: Arc<dyn RefreshTokenStore>) -> Self {
+        self.refresh_store = Some(store);
+        self
+    }
+
+    /// Enables access token revocation, denying the tokens listed in `store`.
+    ///
+    /// See [`Jwt::revoke`].
+    pub fn with_revocation_store(mut self, store: Arc<dyn RevocationStore>) -> Self {
+        self.revocation_store = Some(store);
+        self
+    }
+
+    /// Enables single-use signed URLs, tracking consumed URLs in `store`.
+    ///
+    /// See [`Jwt::sign_url_once`].
+    pub fn with_consumed_token_store(mut self, store: Arc<dyn ConsumedTokenStore>) -> Self {
+        self.consumed_store = Some(store);
+        self
+    }
+
+    /// Returns `true` if the instance can issue access tokens, `false` if it is verify-only.
+    pub fn can_sign(&self) -> bool {
+        self.encoding_access_key.is_some()
+    }
+
+    /// Returns the set of keys access tokens are verified against by `kid`.
+    pub fn key_set(&self) -> &Arc<KeySet> {
+        &self.key_set
+    }
+
+    /// Returns the public keys of the key set as a JWKS document.
+    ///
+    /// Only keys with a `kid` are listed, and HMAC keys never are.
+    pub fn jwks(&self) -> JwkSet {
+        self.key_set.to_jwks()
+    }
+
+    /// Reloads the keys of the configured JWKS, if any.
+    pub async fn refresh_jwks(&self) -> Result<()> {
+        match &self.jwks_source {
+            Some(source) => self.key_set.refresh(source).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Starts refreshing the keys of the configured JWKS periodically, if any.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn spawn_jwks_refresh(&self) -> Option<JoinHandle<()>> {
+        let source = self.jwks_source.clone()?;
+        Some(
+            self.key_set
+                .spawn_refresh(source, self.jwks_refresh_interval),
+        )
+    }
+
+    /// Generates a pair of access and refresh tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub` - The subject for which the tokens are generated.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing a tuple of the access token and the refresh token, or an `Error`.
+    pub fn generate_token_pair(&self, sub: String) -> Result<(String, String)> {
+        self.generate_token_pair_with(sub, &())
+    }
+
+    /// Generates a pair of access and refresh tokens carrying application claims.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub` - The subject for which the tokens are generated.
+    /// * `extra` - The application claims, written to both tokens.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing a tuple of the access token and the refresh token, or an `Error`.
+    pub fn generate_token_pair_with<C>(&self, sub: String, extra: &C) -> Result<(String, String)>
+    where
+        C: Serialize,
+    {
+        self.generate_token_pair_granted(sub, &Grants::default(), extra)
+    }
+
+    /// Generates a pair of access and refresh tokens granting scopes and roles.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub` - The subject for which the tokens are generated.
+    /// * `grants` - The scopes and roles, written to both tokens.
+    /// * `extra` - The application claims, written to both tokens.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing a tuple of the access token and the refresh token, or an `Error`.
+    pub fn generate_token_pair_granted<C>(
+        &self,
+        sub: String,
+        grants: &Grants,
+        extra: &C,
+    ) -> Result<(String, String)>
+    where
+        C: Serialize,
+    {
+        self.generate_token_pair_in_family(&sub, grants, extra, &new_token_id())
+    }
+
+    /// Generates an access token.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub` - The subject for which the access token is generated.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the generated access token as a string, or an `Error`.
+    pub fn generate_access_token(&self, sub: String) -> Result<String> {
+        self.generate_access_token_with(sub, &())
+    }
+
+    /// Generates an access token carrying application claims.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub` - The subject for which the access token is generated.
+    /// * `extra` - The application claims.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the generated access token as a string, or an `Error`.
+    pub fn generate_access_token_with<C>(&self, sub: String, extra: &C) -> Result<String>
+    where
+        C: Serialize,
+    {
+        self.generate_access_token_granted(sub, &Grants::default(), extra)
+    }
+
+    /// Generates an access token granting scopes and roles.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub` - The subject for which the access token is generated.
+    /// * `grants` - The scopes and roles.
+    /// * `extra` - The application claims.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the generated access token as a string, or an `Error`.
+    pub fn generate_access_token_granted<C>(
+        &self,
+        sub: String,
+        grants: &Grants,
+        extra: &C,
+    ) -> Result<String>
+    where
+        C: Serialize,
+    {
+        self.generate_token(&TokenKind::ACCESS, &sub, grants, extra, None)
+    }
+
+    /// Generates a partial token, proving that the first authentication factor of `sub`, such as
+    /// its password, was verified.
+    ///
+    /// Partial tokens are short-lived and carry their own audience, so they are rejected
+    /// wherever an access token is expected. Once the second factor is verified, exchange the
+    /// token validated with [`Jwt::validate_partial_token`] for a token pair.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub` - The subject for which the partial token is generated.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the generated partial token as a string, or an `Error`.
+    pub fn generate_partial_token(&self, sub: String) -> Result<String> {
+        self.generate_token(&TokenKind::PARTIAL, &sub, &Grants::default(), &(), None)
+    }
+
+    /// Refreshes an access token using a refresh token.
+    ///
+    /// The refresh token stays valid; use [`Jwt::rotate_refresh_token`] to rotate it instead.
+    /// The refresh token store is not consulted; validate the token with
+    /// [`Jwt::validate_refresh_token_checked`] first to reject rotated or revoked tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `refresh_token` - The refresh token used to generate a new access token.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the new access token, or an `Error`.
+    pub fn refresh_access_token(&self, refresh_token: &str) -> Result<String> {
+        self.refresh_access_token_with::<()>(refresh_token)
+    }
+
+    /// Refreshes an access token using a refresh token, keeping its grants and application
+    /// claims.
+    ///
+    /// # Arguments
+    ///
+    /// * `refresh_token` - The refresh token used to generate a new access token.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the new access token, or an `Error`.
+    pub fn refresh_access_token_with<C>(&self, refresh_token: &str) -> Result<String>
+    where
+        C: Serialize + DeserializeOwned,
+    {
+        let claims = self.validate_refresh_token_with::<C>(refresh_token)?;
+        self.generate_access_token_granted(claims.sub.clone(), &claims.grants(), &claims.extra)
+    }
+
+    /// Exchanges a refresh token for a new token pair, invalidating the refresh token.
+    ///
+    /// Requires a store set with [`Jwt::with_refresh_store`]. Presenting a refresh token that was
+    /// already rotated revokes every refresh token of its family, ending the session both for the
+    /// legitimate client and for whoever replayed the token.
+    ///
+    /// # Arguments
+    ///
+    /// * `refresh_token` - The refresh token to rotate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing a tuple of the access token and the refresh token, or an `Error`.
+    pub async fn rotate_refresh_token(&self, refresh_token: &str) -> Result<(String, String)> {
+        self.rotate_refresh_token_with::<()>(refresh_token).await
+    }
+
+    /// Like [`Jwt::rotate_refresh_token`], keeping the application claims of the token.
+    ///
+    /// # Arguments
+    ///
+    /// * `refresh_token` - The refresh token to rotate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing a tuple of the access token and the refresh token, or an `Error`.
+    pub async fn rotate_refresh_token_with<C>(
+        &self,
+        refresh_token: &str,
+    ) -> Result<(String, String)>
+    where
+        C: Serialize + DeserializeOwned,
+    {
+        let store = self
+            .refresh_store
+            .as_ref()
+            .ok_or_else(|| Error::SystemError("no refresh token store configured".to_string()))?;
+        let claims = self
+            .validate_refresh_token_checked_with::<C>(refresh_token)
+            .await?;
+        let (Some(jti), Some(family)) = (claims.jti.clone(), claims.fam.clone()) else {
+            return Err(Error::AuthError(
+                "refresh token cannot be rotated".to_string(),
+            ));
+        };
+        // Marking the token as used is the atomic check, in case it is replayed concurrently.
+        if !store.mark_used(jti, claims.exp).await? {
+            return Err(self.revoke_family(store.as_ref(), family).await);
+        }
+        self.generate_token_pair_in_family(&claims.sub, &claims.grants(), &claims.extra, &family)
+    }
+
+    /// Revokes an access token, which is then rejected until it expires.
+    ///
+    /// Requires a store set with [`Jwt::with_revocation_store`]. Tokens issued before tokens
+    /// carried a `jti` cannot be revoked.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The access token to revoke.
+    ///
+    /// # Returns
+    ///
+    /// * An empty `Result`, or an `Error` if the token is invalid or cannot be revoked.
+    pub async fn revoke(&self, token: &str) -> Result<()> {
+        let store = self
+            .revocation_store
+            .as_ref()
+            .ok_or_else(|| Error::SystemError("no revocation store configured".to_string()))?;
+        let claims = self.validate_access_token(token)?;
+        let jti = claims
+            .jti
+            .ok_or_else(|| Error::AuthError("token cannot be revoked".to_string()))?;
+        store.revoke(jti, claims.exp).await
+    }
+
+    /// Validates an access token.
+    ///
+    /// Tokens revoked with [`Jwt::revoke`] are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The access token to validate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the `Claims` if validation is successful, or an `Error`.
+    pub fn validate_access_token(&self, token: &str) -> Result<Claims> {
+        self.validate_access_token_with(token)
+    }
+
+    /// Validates an access token and decodes its application claims as `C`.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The access token to validate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the `Claims` if validation is successful, or an `Error`.
+    pub fn validate_access_token_with<C>(&self, token: &str) -> Result<Claims<C>>
+    where
+        C: DeserializeOwned,
+    {
+        self.validate_token(&TokenKind::ACCESS, token)
+            .map(|data| data.claims)
+    }
+
+    /// Validates a refresh token.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token to validate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the `Claims` if validation is successful, or an `Error`.
+    pub fn validate_refresh_token(&self, token: &str) -> Result<Claims> {
+        self.validate_refresh_token_with(token)
+    }
+
+    /// Validates a refresh token and decodes its application claims as `C`.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token to validate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the `Claims` if validation is successful, or an `Error`.
+    pub fn validate_refresh_token_with<C>(&self, token: &str) -> Result<Claims<C>>
+    where
+        C: DeserializeOwned,
+    {
+        self.validate_token(&TokenKind::REFRESH, token)
+            .map(|data| data.claims)
+    }
+
+    /// Validates a refresh token and checks it against the refresh token store.
+    ///
+    /// With a store set with [`Jwt::with_refresh_store`], tokens of a revoked family are rejected
+    /// with [`TokenError::Revoked`], as are tokens that were already rotated, which also revokes
+    /// their family. Without a store, this is [`Jwt::validate_refresh_token`].
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token to validate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the `Claims` if validation is successful, or an `Error`.
+    pub async fn validate_refresh_token_checked(&self, token: &str) -> Result<Claims> {
+        self.validate_refresh_token_checked_with(token).await
+    }
+
+    /// Like [`Jwt::validate_refresh_token_checked`], decoding the application claims as `C`.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The refresh token to validate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the `Claims` if validation is successful, or an `Error`.
+    pub async fn validate_refresh_token_checked_with<C>(&self, token: &str) -> Result<Claims<C>>
+    where
+        C: DeserializeOwned,
+    {
+        let claims = self.validate_refresh_token_with::<C>(token)?;
+        if let (Some(store), Some(jti), Some(family)) =
+            (&self.refresh_store, &claims.jti, &claims.fam)
+        {
+            if store.is_family_revoked(family.clone()).await? {
+                return Err(TokenError::Revoked.into());
+            }
+            if store.is_used(jti.clone()).await? {
+                return Err(self.revoke_family(store.as_ref(), family.clone()).await);
+            }
+        }
+        Ok(claims)
+    }
+
+    /// Validates a partial token issued by [`Jwt::generate_partial_token`].
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The partial token to validate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the `Claims` if validation is successful, or an `Error`.
+    pub fn validate_partial_token(&self, token: &str) -> Result<Claims> {
+        self.validate_token(&TokenKind::PARTIAL, token)
+            .map(|data| data.claims)
+    }
+
+    /// Generates a token based on the token kind and subject.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The type of token (ACCESS or REFRESH).
+    /// * `sub` - The subject for which the token is generated.
+    /// * `grants` - The scopes and roles.
+    /// * `extra` - The application claims.
+    /// * `family` - The refresh token family, for refresh tokens.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the generated token as a string, or an `Error`.
+    fn generate_token<C>(
+        &self,
+        kind: &TokenKind,
+        sub: &str,
+        grants: &Grants,
+        extra: &C,
+        family: Option<&str>,
+    ) -> Result<String>
+    where
+        C: Serialize,
+    {
+        let duration = self.get_token_duration(kind);
+        let (iat, exp) = self.generate_timestamps(duration);
+        let (header, key) = self.select_header_and_encoding_key(kind)?;
+        let mut claims = self.create_claims(sub, iat, exp, extra);
+        if let TokenKind::PARTIAL = kind {
+            claims.aud = partial_audience(&self.aud);
+        }
+        claims.iss = self.iss.clone();
+        claims.nbf = Some(iat);
+        claims.jti = Some(new_token_id());
+        claims.fam = family.map(str::to_string);
+        claims.set_grants(grants);
+        let token = encode(header, &claims, key)?;
+        self.encrypt(token)
+    }
+
+    /// Revokes `family` after one of its tokens was reused, returning the error to report.
+    async fn revoke_family(&self, store: &dyn RefreshTokenStore, family: String) -> Error {
+        let (_, exp) = self.generate_timestamps(self.refresh_token_duration);
+        match store.revoke_family(family, exp).await {
+            Ok(()) => TokenError::Revoked.into(),
+            Err(e) => e,
+        }
+    }
+
+    /// Generates a pair of access and refresh tokens, the refresh token belonging to `family`.
+    fn generate_token_pair_in_family<C>(
+        &self,
+        sub: &str,
+        grants: &Grants,
+        extra: &C,
+        family: &str,
+    ) -> Result<(String, String)>
+    where
+        C: Serialize,
+    {
+        let access_token = self.generate_token(&TokenKind::ACCESS, sub, grants, extra, None)?;
+        let refresh_token =
+            self.generate_token(&TokenKind::REFRESH, sub, grants, extra, Some(family))?;
+        Ok((access_token, refresh_token))
+    }
+
+    /// Validates a token based on the token kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The type of token (ACCESS or REFRESH).
+    /// * `token` - The token to validate.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing `TokenData<Claims>` if validation is successful, or an `Error`.
+    fn validate_token<C>(&self, kind: &TokenKind, token: &str) -> Result<TokenData<Claims<C>>>
+    where
+        C: DeserializeOwned,
+    {
+        let data = self.decode_token::<C>(kind, token)?;
+        if let (TokenKind::ACCESS, Some(store), Some(jti)) =
+            (kind, &self.revocation_store, &data.claims.jti)
+            && store.is_revoked(jti)
+        {
+            return Err(TokenError::Revoked.into());
+        }
+        Ok(data)
+    }
+
+    /// Decodes and verifies a token based on the token kind.
+    fn decode_token<C>(&self, kind: &TokenKind, token: &str) -> Result<TokenData<Claims<C>>>
+    where
+        C: DeserializeOwned,
+    {
+        let token = self.decrypt(token)?;
+        let token = token.as_ref();
+        if !matches!(kind, TokenKind::REFRESH) {
+            let kid = decode_header(token).map_err(error::classify)?.kid;
+            if let Some(key) = kid.and_then(|kid| self.key_set.get(&kid)) {
+                let mut validation = self.select_validation(kind).clone();
+                validation.algorithms = vec![key.algorithm];
+                return decode::<Claims<C>>(token, key.decoding_key(), &validation)
+                    .map_err(error::classify);
+            }
+        }
+        let (key, validation) = self.select_decoding_key_and_validation(kind)?;
+        decode::<Claims<C>>(token, key, validation).map_err(error::classify)
+    }
+
+    /// Encrypts a signed token if the instance has an encryption key.
+    fn encrypt(&self, token: String) -> Result<String> {
+        #[cfg(feature = "jwe")]
+        if let Some(key) = &self.encryption {
+            return key.encrypt(&token);
+        }
+        Ok(token)
+    }
+
+    /// Decrypts an encrypted token, returning signed tokens as is.
+    fn decrypt<'a>(&self, token: &'a str) -> Result<Cow<'a, str>> {
+        #[cfg(feature = "jwe")]
+        if jwe::is_jwe(token) {
+            let key = self
+                .encryption
+                .as_ref()
+                .ok_or(Error::TokenError(TokenError::Malformed))?;
+            return key.decrypt(token).map(Cow::Owned);
+        }
+        Ok(Cow::Borrowed(token))
+    }
+
+    /// Selects the appropriate token duration based on the token kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The type of token (ACCESS or REFRESH).
+    ///
+    /// # Returns
+    ///
+    /// * The token duration in seconds.
+    fn get_token_duration(&self, kind: &TokenKind) -> usize {
+        match kind {
+            TokenKind::ACCESS => self.access_token_duration,
+            TokenKind::REFRESH => self.refresh_token_duration,
+            TokenKind::PARTIAL => self.partial_token_duration,
+        }
+    }
+
+    /// Generates the issued at (iat) and expiration (exp) times based on the provided duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The duration in seconds for which the token is valid.
+    ///
+    /// # Returns
+    ///
+    /// * A tuple containing the issued at time and expiration time as UNIX timestamps.
+    fn generate_timestamps(&self, duration: usize) -> (usize, usize) {
+        generate_expired_time(duration)
+    }
+
+    /// Selects the appropriate header and encoding key based on the token kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The type of token (ACCESS or REFRESH).
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the selected `Header` and `EncodingKey`, or an `Error` if the
+    ///   instance has no key to sign this kind of token.
+    fn select_header_and_encoding_key(&self, kind: &TokenKind) -> Result<(&Header, &EncodingKey)> {
+        let (header, key) = match kind {
+            TokenKind::ACCESS | TokenKind::PARTIAL => (&self.header, &self.encoding_access_key),
+            TokenKind::REFRESH => (&self.refresh_header, &self.encoding_refresh_key),
+        };
+        let key = key.as_ref().ok_or_else(|| {
+            Error::SystemError(format!("no signing key configured for {} tokens", kind))
+        })?;
+        Ok((header, key))
+    }
+
+    /// Creates a new `Claims` instance for the given subject, issued at time, and expiration time.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub` - The subject for which the claims are generated.
+    /// * `iat` - The issued at time.
+    /// * `exp` - The expiration time.
+    /// * `extra` - The application claims.
+    ///
+    /// # Returns
+    ///
+    /// * A new `Claims` instance.
+    fn create_claims<'a, C>(
+        &self,
+        sub: &str,
+        iat: usize,
+        exp: usize,
+        extra: &'a C,
+    ) -> Claims<&'a C> {
+        Claims::with_extra(self.aud.clone(), sub.to_string(), exp, iat, extra)
+    }
+
+    /// Selects the appropriate decoding key and validation based on the token kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The type of token (ACCESS or REFRESH).
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the selected `DecodingKey` and `Validation`, or an `Error` if
+    ///   refresh tokens are disabled.
+    fn select_decoding_key_and_validation(
+        &self,
+        kind: &TokenKind,
+    ) -> Result<(&DecodingKey, &Validation)> {
+        let key = match kind {
+            TokenKind::ACCESS | TokenKind::PARTIAL => {
+                // Tokens naming an unknown key are rejected when there is no default key.
+                self.decoding_access_key
+                    .as_ref()
+                    .ok_or(Error::TokenError(TokenError::InvalidSignature))?
+            }
+            TokenKind::REFRESH => self.decoding_refresh_key.as_ref().ok_or_else(|| {
+                Error::SystemError("no key configured for refresh tokens".to_string())
+            })?,
+        };
+        Ok((key, self.select_validation(kind)))
+    }
+
+    /// Selects the validation based on the token kind.
+    fn select_validation(&self, kind: &TokenKind) -> &Validation {
+        match kind {
+            TokenKind::ACCESS => &self.validation_access_key,
+            TokenKind::REFRESH => &self.validation_refresh_key,
+            TokenKind::PARTIAL => &self.validation_partial_key,
+        }
+    }
+}
+
+/// Generates a random token id.
+fn new_token_id() -> String {
+    crate::utils::crypto_util::random_hex(16)
+}
+
+const DEFAULT_JWKS_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(300);
+
+const DEFAULT_PARTIAL_TOKEN_DURATION: usize = 300;
+
+/// Returns the audience of partial tokens, which access token validation never accepts.
+fn partial_audience(audience: &str) -> String {
+    format!("{}#partial", audience)
+}
+
+/// Builds the error for a key that `algorithm` needs but the configuration lacks.
+fn missing_key(field: &str, algorithm: Algorithm) -> Error {
+    Error::ConfigError(config::ConfigError::Message(format!(
+        "jwt.{} is required for {:?}",
+        field, algorithm
+    )))
+}
+
+/// Returns the current time as a UNIX timestamp.
+pub(crate) fn now() -> usize {
+    Utc::now().timestamp() as usize
+}
+
+/// Generates the issued at (iat) and expiration (exp) times based on the provided duration.
+///
+/// # Arguments
+///
+/// * `duration` - The duration in seconds for which the token is valid.
+///
+/// # Returns
+///
+/// * A tuple containing the issued at time and expiration time as UNIX timestamps.
+fn generate_expired_time(duration: usize) -> (usize, usize) {
+    let now = Utc::now();
+    let iat = now.timestamp() as usize;
+    let exp = (now + Duration::seconds(duration as i64)).timestamp() as usize;
+    (iat, exp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sets up a `Jwt` instance for testing.
+    ///
+    /// # Returns
+    ///
+    /// * A `Jwt` instance with test configuration.
+    fn setup_jwt() -> Jwt {
+        Jwt::new(JwtCfg {
+            access_secret: "access_secret".into(),
+            refresh_secret: "refresh_secret".into(),
+            audience: "test_audience".to_string(),
+            access_token_duration: ConfigDuration::from_secs(3600), // 1 hour
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            access_key_validate_exp: true,
+            refresh_key_validate_exp: true,
+            ..Default::default()
+        })
+    }
+
+    /// Sets up a `Jwt` instance using `algorithm` and the test keys in `tests/keys`.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The asymmetric algorithm to use.
+    /// * `key` - The key file prefix: `rsa`, `ec` or `ed`.
+    /// * `ext` - The key file extension: `pem` or `der`.
+    /// * `sign` - Whether the instance gets the private key.
+    fn setup_asymmetric_jwt(algorithm: Algorithm, key: &str, ext: &str, sign: bool) -> Jwt {
+        Jwt::new(JwtCfg {
+            algorithm,
+            refresh_secret: if sign {
+                "refresh_secret".into()
+            } else {
+                "".into()
+            },
+            private_key_path: sign.then(|| format!("tests/keys/{}_private.{}", key, ext)),
+            public_key_path: Some(format!("tests/keys/{}_public.{}", key, ext)),
+            audience: "test_audience".to_string(),
+            access_token_duration: ConfigDuration::from_secs(3600),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            access_key_validate_exp: true,
+            refresh_key_validate_exp: true,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_validate_cfg() {
+        let cfg = JwtCfg {
+            access_secret: "".into(),
+            refresh_secret: "refresh_secret".into(),
+            audience: "".to_string(),
+            access_token_duration: ConfigDuration::from_secs(0),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            partial_token_duration: Some(std::time::Duration::from_millis(500).into()),
+            access_key_validate_exp: true,
+            refresh_key_validate_exp: true,
+            ..Default::default()
+        };
+
+        let mut errors = ValidationErrors::new();
+        errors.nested("jwt", &cfg);
+        let paths: Vec<&str> = errors.errors().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "jwt.access_secret",
+                "jwt.audience",
+                "jwt.access_token_duration",
+                "jwt.partial_token_duration"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cfg_accepts_seconds_and_duration_strings() {
+        let cfg: JwtCfg = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                access_secret = "access_secret"
+                refresh_secret = "refresh_secret"
+                audience = "test_audience"
+                access_token_duration = 10800
+                refresh_token_duration = "7d"
+                access_key_validate_exp = true
+                refresh_key_validate_exp = true
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        assert_eq!(cfg.access_token_duration.as_secs(), 10800);
+        assert_eq!(cfg.refresh_token_duration.as_secs(), 604800);
+    }
+
+    #[test]
+    fn test_generate_token_pair() {
+        let jwt = setup_jwt();
+        let (access_token, refresh_token) =
+            jwt.generate_token_pair("test_sub".to_string()).unwrap();
+
+        assert!(!access_token.is_empty());
+        assert!(!refresh_token.is_empty());
+    }
+
+    #[test]
+    fn test_generate_access_token() {
+        let jwt = setup_jwt();
+        let access_token = jwt.generate_access_token("test_sub".to_string()).unwrap();
+
+        assert!(!access_token.is_empty());
+    }
+
+    #[test]
+    fn test_validate_access_token() {
+        let jwt = setup_jwt();
+        let access_token = jwt.generate_access_token("test_sub".to_string()).unwrap();
+        let validation_result = jwt.validate_access_token(&access_token);
+
+        assert!(validation_result.is_ok());
+        let claims = validation_result.unwrap();
+        assert_eq!(claims.aud, "test_audience");
+        assert_eq!(claims.sub, "test_sub");
+    }
+
+    #[test]
+    fn test_validate_refresh_token() {
+        let jwt = setup_jwt();
+        let (_, refresh_token) = jwt.generate_token_pair("test_sub".to_string()).unwrap();
+        let validation_result = jwt.validate_refresh_token(&refresh_token);
+
+        assert!(validation_result.is_ok());
+        let claims = validation_result.unwrap();
+        assert_eq!(claims.aud, "test_audience");
+        assert_eq!(claims.sub, "test_sub");
+    }
+
+    #[test]
+    fn test_expired_access_token() {
+        use std::time::{Duration as StdDuration, SystemTime, UNIX_EPOCH};
+
+        let jwt = setup_jwt();
+        // Manually generate an expired token
+        let iat = (SystemTime::now() - StdDuration::from_secs(7200))
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as usize;
+        let exp = (SystemTime::now() - StdDuration::from_secs(3600))
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as usize;
+        let claims = Claims::new(
+            "test_audience".to_string(),
+            "test_sub".to_string(),
+            exp,
+            iat,
+        );
+        let access_token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret("access_secret".as_ref()),
+        )
+        .unwrap();
+
+        let validation_result = jwt.validate_access_token(&access_token);
+
+        assert!(validation_result.is_err());
+        match validation_result.unwrap_err() {
+            Error::TokenError(TokenError::Expired) => (),
+            _ => panic!("Expected TokenError::Expired"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_access_token() {
+        let jwt = setup_jwt();
+        let invalid_token = "invalid_token";
+
+        let validation_result = jwt.validate_access_token(invalid_token);
+
+        assert!(validation_result.is_err());
+        match validation_result.unwrap_err() {
+            Error::TokenError(TokenError::Malformed) => (),
+            _ => panic!("Expected TokenError::Malformed"),
+        }
+    }
+
+    #[test]
+    fn test_refresh_access_token() {
+        let jwt = setup_jwt();
+        let (_, refresh_token) = jwt.generate_token_pair("test_sub".to_string()).unwrap();
+
+        let new_access_token = jwt.refresh_access_token(&refresh_token).unwrap();
+
+        assert!(!new_access_token.is_empty());
+    }
+
+    #[test]
+    fn test_asymmetric_algorithms() {
+        for (algorithm, key) in [
+            (Algorithm::RS256, "rsa"),
+            (Algorithm::PS256, "rsa"),
+            (Algorithm::ES256, "ec"),
+            (Algorithm::EdDSA, "ed"),
+        ] {
+            for ext in ["pem", "der"] {
+                let signer = setup_asymmetric_jwt(algorithm, key, ext, true);
+                let verifier = setup_asymmetric_jwt(algorithm, key, ext, false);
+                let (access_token, refresh_token) =
+                    signer.generate_token_pair("test_sub".to_string()).unwrap();
+
+                let header = jsonwebtoken::decode_header(&access_token).unwrap();
+                assert_eq!(header.alg, algorithm);
+                let claims = verifier.validate_access_token(&access_token).unwrap();
+                assert_eq!(claims.sub, "test_sub", "{:?} {}", algorithm, ext);
+                assert!(signer.validate_refresh_token(&refresh_token).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_only_instance() {
+        let verifier = setup_asymmetric_jwt(Algorithm::RS256, "rsa", "pem", false);
+        assert!(!verifier.can_sign());
+        assert!(
+            verifier
+                .generate_access_token("test_sub".to_string())
+                .is_err()
+        );
+
+        let (_, refresh_token) = setup_asymmetric_jwt(Algorithm::RS256, "rsa", "pem", true)
+            .generate_token_pair("test_sub".to_string())
+            .unwrap();
+        assert!(verifier.validate_refresh_token(&refresh_token).is_err());
+    }
+
+    #[test]
+    fn test_rejects_other_algorithms_and_keys() {
+        let rsa = setup_asymmetric_jwt(Algorithm::RS256, "rsa", "pem", true);
+        let hmac = setup_jwt();
+        let token = hmac.generate_access_token("test_sub".to_string()).unwrap();
+        assert!(matches!(
+            rsa.validate_access_token(&token),
+            Err(Error::TokenError(TokenError::InvalidSignature))
+        ));
+
+        let ec = setup_asymmetric_jwt(Algorithm::ES256, "ec", "pem", true);
+        let token = ec.generate_access_token("test_sub".to_string()).unwrap();
+        assert!(rsa.validate_access_token(&token).is_err());
+    }
+
+    #[test]
+    fn test_try_new_reports_missing_key() {
+        let err = Jwt::try_new(JwtCfg {
+            algorithm: Algorithm::ES256,
+            audience: "test_audience".to_string(),
+            ..Default::default()
+        })
+        .err()
+        .unwrap();
+        assert!(
+            err.to_string()
+                .contains("jwt.public_key_path is required for ES256")
+        );
+        // HMAC instances with an empty secret are rejected by validation only, as before.
+        assert!(
+            Jwt::try_new(JwtCfg {
+                audience: "test_audience".to_string(),
+                ..Default::default()
+            })
+            .is_ok()
+        );
+
+        let mut errors = ValidationErrors::new();
+        JwtCfg {
+            algorithm: Algorithm::ES256,
+            private_key_path: Some("tests/keys/ec_private.pem".to_string()),
+            audience: "test_audience".to_string(),
+            access_token_duration: ConfigDuration::from_secs(3600),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            ..Default::default()
+        }
+        .validate(&mut errors);
+        let paths: Vec<&str> = errors.errors().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["public_key_path", "refresh_secret"]);
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let cfg = |algorithm, key: &str, kid: &str, verify_keys| JwtCfg {
+            algorithm,
+            refresh_secret: "refresh_secret".into(),
+            private_key_path: Some(format!("tests/keys/{}_private.pem", key)),
+            public_key_path: Some(format!("tests/keys/{}_public.pem", key)),
+            kid: Some(kid.to_string()),
+            verify_keys,
+            audience: "test_audience".to_string(),
+            access_token_duration: ConfigDuration::from_secs(3600),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            ..Default::default()
+        };
+        let old = Jwt::new(cfg(Algorithm::RS256, "rsa", "2024-01", Vec::new()));
+        let old_token = old.generate_access_token("old_sub".to_string()).unwrap();
+        assert_eq!(
+            decode_header(&old_token).unwrap().kid.as_deref(),
+            Some("2024-01")
+        );
+
+        let new = Jwt::new(cfg(
+            Algorithm::ES256,
+            "ec",
+            "2024-02",
+            vec![JwtKeyCfg {
+                kid: "2024-01".to_string(),
+                algorithm: Algorithm::RS256,
+                public_key_path: Some("tests/keys/rsa_public.pem".to_string()),
+                ..Default::default()
+            }],
+        ));
+        let new_token = new.generate_access_token("new_sub".to_string()).unwrap();
+        assert_eq!(
+            new.validate_access_token(&old_token).unwrap().sub,
+            "old_sub"
+        );
+        assert_eq!(
+            new.validate_access_token(&new_token).unwrap().sub,
+            "new_sub"
+        );
+        assert!(old.validate_access_token(&new_token).is_err());
+        assert_eq!(new.key_set().kids(), ["2024-01", "2024-02"]);
+
+        // A verify-only instance trusting the published keys only.
+        let path = std::env::temp_dir().join(format!("sur_jwt_jwks_{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_vec(&new.jwks()).unwrap()).unwrap();
+        let verifier = Jwt::new(JwtCfg {
+            algorithm: Algorithm::ES256,
+            jwks_path: Some(path.to_str().unwrap().to_string()),
+            audience: "test_audience".to_string(),
+            ..Default::default()
+        });
+        std::fs::remove_file(&path).unwrap();
+        assert!(!verifier.can_sign());
+        assert_eq!(
+            verifier.validate_access_token(&old_token).unwrap().sub,
+            "old_sub"
+        );
+        assert_eq!(
+            verifier.validate_access_token(&new_token).unwrap().sub,
+            "new_sub"
+        );
+        let unknown = setup_asymmetric_jwt(Algorithm::ES256, "ec", "pem", true)
+            .generate_access_token("test_sub".to_string())
+            .unwrap();
+        assert!(verifier.validate_access_token(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_custom_claims() {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Session {
+            groups: Vec<String>,
+            tenant: String,
+        }
+
+        let jwt = setup_jwt();
+        let session = Session {
+            groups: vec!["staff".to_string()],
+            tenant: "acme".to_string(),
+        };
+        let (access_token, refresh_token) = jwt
+            .generate_token_pair_with("test_sub".to_string(), &session)
+            .unwrap();
+
+        let claims = jwt
+            .validate_access_token_with::<Session>(&access_token)
+            .unwrap();
+        assert_eq!(claims.sub, "test_sub");
+        assert_eq!(claims.extra, session);
+        // Application claims are ignored when not asked for.
+        assert_eq!(
+            jwt.validate_access_token(&access_token).unwrap().sub,
+            "test_sub"
+        );
+        assert!(
+            jwt.validate_access_token_with::<Session>(
+                &jwt.generate_access_token("test_sub".to_string()).unwrap()
+            )
+            .is_err()
+        );
+
+        let access_token = jwt
+            .refresh_access_token_with::<Session>(&refresh_token)
+            .unwrap();
+        let claims = jwt
+            .validate_access_token_with::<Session>(&access_token)
+            .unwrap();
+        assert_eq!(claims.extra, session);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_refresh_token() {
+        let jwt = setup_jwt();
+        let (_, refresh_token) = jwt.generate_token_pair("test_sub".to_string()).unwrap();
+        assert!(jwt.rotate_refresh_token(&refresh_token).await.is_err());
+
+        let jwt = jwt.with_refresh_store(Arc::new(refresh::InMemoryRefreshTokenStore::new()));
+        let family = jwt.validate_refresh_token(&refresh_token).unwrap().fam;
+        assert!(family.is_some());
+
+        let (access_token, rotated) = jwt.rotate_refresh_token(&refresh_token).await.unwrap();
+        assert_eq!(
+            jwt.validate_access_token(&access_token).unwrap().sub,
+            "test_sub"
+        );
+        let claims = jwt.validate_refresh_token(&rotated).unwrap();
+        assert_eq!(claims.fam, family);
+        let (_, latest) = jwt.rotate_refresh_token(&rotated).await.unwrap();
+
+        // Replaying a rotated token revokes the family, including the latest token.
+        assert!(matches!(
+            jwt.rotate_refresh_token(&refresh_token).await,
+            Err(Error::TokenError(TokenError::Revoked))
+        ));
+        assert!(jwt.rotate_refresh_token(&latest).await.is_err());
+
+        let (_, other) = jwt.generate_token_pair("test_sub".to_string()).unwrap();
+        assert!(jwt.rotate_refresh_token(&other).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_refresh_token_checked() {
+        let store = Arc::new(refresh::InMemoryRefreshTokenStore::new());
+        let jwt = setup_jwt().with_refresh_store(store.clone());
+
+        let (_, revoked) = jwt.generate_token_pair("test_sub".to_string()).unwrap();
+        let claims = jwt.validate_refresh_token_checked(&revoked).await.unwrap();
+        store
+            .revoke_family(claims.fam.unwrap(), now() + 60)
+            .await
+            .unwrap();
+        assert!(matches!(
+            jwt.validate_refresh_token_checked(&revoked).await,
+            Err(Error::TokenError(TokenError::Revoked))
+        ));
+        // The store is only consulted by the checked variants.
+        assert!(jwt.validate_refresh_token(&revoked).is_ok());
+
+        let (_, refresh_token) = jwt.generate_token_pair("test_sub".to_string()).unwrap();
+        let (_, rotated) = jwt.rotate_refresh_token(&refresh_token).await.unwrap();
+        assert!(jwt.validate_refresh_token_checked(&rotated).await.is_ok());
+
+        // A rotated token is rejected, and using it revokes the family.
+        assert!(matches!(
+            jwt.validate_refresh_token_checked(&refresh_token).await,
+            Err(Error::TokenError(TokenError::Revoked))
+        ));
+        assert!(matches!(
+            jwt.validate_refresh_token_checked(&rotated).await,
+            Err(Error::TokenError(TokenError::Revoked))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_revoke() {
+        let jwt = setup_jwt();
+        let token = jwt.generate_access_token("test_sub".to_string()).unwrap();
+        assert!(jwt.revoke(&token).await.is_err());
+
+        let jwt = jwt.with_revocation_store(Arc::new(revocation::InMemoryRevocationStore::new()));
+        let other = jwt.generate_access_token("test_sub".to_string()).unwrap();
+        assert!(jwt.validate_access_token(&token).unwrap().jti.is_some());
+        jwt.revoke(&token).await.unwrap();
+
+        let err = jwt.validate_access_token(&token).unwrap_err();
+        assert!(matches!(err, Error::TokenError(TokenError::Revoked)));
+        assert!(jwt.revoke(&token).await.is_err());
+        assert!(jwt.validate_access_token(&other).is_ok());
+    }
+
+    #[test]
+    fn test_registered_claims() {
+        let cfg = |issuer: &str, audience: &str, accepted_audiences: &[&str]| JwtCfg {
+            access_secret: "access_secret".into(),
+            refresh_secret: "refresh_secret".into(),
+            issuer: Some(issuer.to_string()),
+            audience: audience.to_string(),
+            accepted_audiences: accepted_audiences.iter().map(|a| a.to_string()).collect(),
+            leeway: Some(ConfigDuration::from_secs(5)),
+            access_token_duration: ConfigDuration::from_secs(3600),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            access_key_validate_exp: true,
+            refresh_key_validate_exp: true,
+            ..Default::default()
+        };
+        let jwt = Jwt::new(cfg("https://auth.prod", "api", &["admin"]));
+        let (access_token, refresh_token) =
+            jwt.generate_token_pair("test_sub".to_string()).unwrap();
+        let claims = jwt.validate_access_token(&access_token).unwrap();
+        assert_eq!(claims.iss.as_deref(), Some("https://auth.prod"));
+        assert_eq!(claims.nbf, Some(claims.iat));
+        assert!(jwt.validate_refresh_token(&refresh_token).is_ok());
+
+        // Another environment or service.
+        let staging = Jwt::new(cfg("https://auth.staging", "api", &[]));
+        assert!(matches!(
+            staging.validate_access_token(&access_token),
+            Err(Error::TokenError(TokenError::InvalidIssuer))
+        ));
+        assert!(staging.validate_refresh_token(&refresh_token).is_err());
+        let admin = Jwt::new(cfg("https://auth.prod", "admin", &[]));
+        let admin_token = admin.generate_access_token("test_sub".to_string()).unwrap();
+        assert!(jwt.validate_access_token(&admin_token).is_ok());
+        assert!(matches!(
+            admin.validate_access_token(&access_token),
+            Err(Error::TokenError(TokenError::InvalidAudience))
+        ));
+
+        // Tokens without an issuer are rejected once one is configured.
+        let token = setup_jwt()
+            .generate_access_token("test_sub".to_string())
+            .unwrap();
+        let no_issuer = Jwt::new(cfg("https://auth.prod", "test_audience", &[]));
+        assert!(no_issuer.validate_access_token(&token).is_err());
+
+        let (iat, exp) = generate_expired_time(3600);
+        let mut claims = Claims::new("api".to_string(), "test_sub".to_string(), exp, iat);
+        claims.iss = Some("https://auth.prod".to_string());
+        let key = EncodingKey::from_secret(b"access_secret");
+        claims.nbf = Some(iat + 3);
+        let token = encode(&Header::default(), &claims, &key).unwrap();
+        assert!(jwt.validate_access_token(&token).is_ok());
+        claims.nbf = Some(iat + 60);
+        let token = encode(&Header::default(), &claims, &key).unwrap();
+        assert!(matches!(
+            jwt.validate_access_token(&token),
+            Err(Error::TokenError(TokenError::NotYetValid))
+        ));
+    }
+
+    #[test]
+    fn test_grants() {
+        let jwt = setup_jwt();
+        let grants = Grants::new()
+            .with_scopes(["orders:read", "orders:write"])
+            .with_roles(["admin"]);
+        let (access_token, refresh_token) = jwt
+            .generate_token_pair_granted("test_sub".to_string(), &grants, &())
+            .unwrap();
+
+        let claims = jwt.validate_access_token(&access_token).unwrap();
+        assert_eq!(claims.scope.as_deref(), Some("orders:read orders:write"));
+        assert_eq!(claims.grants(), grants);
+
+        let access_token = jwt.refresh_access_token(&refresh_token).unwrap();
+        assert_eq!(
+            jwt.validate_access_token(&access_token).unwrap().grants(),
+            grants
+        );
+
+        let access_token = jwt.generate_access_token("test_sub".to_string()).unwrap();
+        let claims = jwt.validate_access_token(&access_token).unwrap();
+        assert_eq!(claims.scope, None);
+        assert_eq!(claims.grants(), Grants::new());
+    }
+
+    #[test]
+    fn test_partial_token() {
+        let jwt = setup_jwt();
+        let token = jwt.generate_partial_token("test_sub".to_string()).unwrap();
+        let claims = jwt.validate_partial_token(&token).unwrap();
+        assert_eq!(claims.sub, "test_sub");
+        assert_eq!(claims.exp - claims.iat, DEFAULT_PARTIAL_TOKEN_DURATION);
+
+        // Partial and access tokens are not interchangeable.
+        assert!(matches!(
+            jwt.validate_access_token(&token),
+            Err(Error::TokenError(TokenError::InvalidAudience))
+        ));
+        let access_token = jwt.generate_access_token("test_sub".to_string()).unwrap();
+        assert!(jwt.validate_partial_token(&access_token).is_err());
+        let (_, refresh_token) = jwt.generate_token_pair("test_sub".to_string()).unwrap();
+        assert!(jwt.validate_partial_token(&refresh_token).is_err());
+        assert!(jwt.refresh_access_token(&token).is_err());
+    }
+
+    #[cfg(feature = "jwe")]
+    #[test]
+    fn test_encrypted_tokens() {
+        use base64::{Engine, engine::general_purpose::STANDARD};
+
+        let cfg = |key: &[u8]| JwtCfg {
+            access_secret: "access_secret".into(),
+            refresh_secret: "refresh_secret".into(),
+            audience: "test_audience".to_string(),
+            access_token_duration: ConfigDuration::from_secs(3600),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            access_key_validate_exp: true,
+            refresh_key_validate_exp: true,
+            encryption_key: Some(STANDARD.encode(key).into()),
+            ..Default::default()
+        };
+        let jwt = Jwt::new(cfg(&[1; 32]));
+        let tenant = serde_json::json!({ "email": "jane@example.com" });
+        let (access_token, refresh_token) = jwt
+            .generate_token_pair_with("test_sub".to_string(), &tenant)
+            .unwrap();
+        assert!(jwe::is_jwe(&access_token));
+        assert!(jwe::is_jwe(&refresh_token));
+
+        let claims = jwt
+            .validate_access_token_with::<serde_json::Value>(&access_token)
+            .unwrap();
+        assert_eq!(claims.extra["email"], "jane@example.com");
+        let access_token = jwt.refresh_access_token(&refresh_token).unwrap();
+        assert!(jwt.validate_access_token(&access_token).is_ok());
+
+        // Signed tokens are still accepted, encrypted ones need the key.
+        let token = setup_jwt()
+            .generate_access_token("test_sub".to_string())
+            .unwrap();
+        assert!(jwt.validate_access_token(&token).is_ok());
+        assert!(matches!(
+            setup_jwt().validate_access_token(&access_token),
+            Err(Error::TokenError(TokenError::Malformed))
+        ));
+        assert!(matches!(
+            Jwt::new(cfg(&[2; 32])).validate_access_token(&access_token),
+            Err(Error::TokenError(TokenError::InvalidSignature))
+        ));
+
+        let mut errors = ValidationErrors::new();
+        cfg(&[1; 16]).validate(&mut errors);
+        assert!(!errors.is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use super::now;
+pub use crate::services::StoreFuture;
+
+/// Keeps track of rotated refresh tokens to detect their reuse.
+///
+/// Every refresh token carries a `jti` and the id of its family, the chain of tokens issued from
+/// one login. A rotated token is marked as used; presenting it again means it leaked, and the
+/// whole family is revoked. Expiry times are UNIX timestamps after which an entry may be dropped.
+pub trait RefreshTokenStore: Send + Sync {
+    /// Marks the token `jti` as used, returning `false` if it already was.
+    fn mark_used(&self, jti: String, exp: usize) -> StoreFuture<'_, bool>;
+
+    /// Returns `true` if the token `jti` was marked as used.
+    fn is_used(&self, jti: String) -> StoreFuture<'_, bool>;
+
+    /// Revokes every token of `family`.
+    fn revoke_family(&self, family: String, exp: usize) -> StoreFuture<'_, ()>;
+
+    /// Returns `true` if `family` was revoked.
+    fn is_family_revoked(&self, family: String) -> StoreFuture<'_, bool>;
+}
+
+/// A [`RefreshTokenStore`] kept in memory, for a single instance or tests.
+#[derive(Debug, Default)]
+pub struct InMemoryRefreshTokenStore {
+    used: Mutex<HashMap<String, usize>>,
+    revoked: Mutex<HashMap<String, usize>>,
+}
+
+impl InMemoryRefreshTokenStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RefreshTokenStore for InMemoryRefreshTokenStore {
+    fn mark_used(&self, jti: String, exp: usize) -> StoreFuture<'_, bool> {
+        Box::pin(async move {
+            let now = now();
+            let mut used = self.used.lock().unwrap();
+            used.retain(|_, exp| *exp >= now);
+            Ok(used.insert(jti, exp).is_none())
+        })
+    }
+
+    fn is_used(&self, jti: String) -> StoreFuture<'_, bool> {
+        Box::pin(async move {
+            let used = self.used.lock().unwrap();
+            Ok(used.get(&jti).is_some_and(|exp| *exp >= now()))
+        })
+    }
+
+    fn revoke_family(&self, family: String, exp: usize) -> StoreFuture<'_, ()> {
+        Box::pin(async move {
+            let now = now();
+            let mut revoked = self.revoked.lock().unwrap();
+            revoked.retain(|_, exp| *exp >= now);
+            revoked.insert(family, exp);
+            Ok(())
+        })
+    }
+
+    fn is_family_revoked(&self, family: String) -> StoreFuture<'_, bool> {
+        Box::pin(async move { Ok(self.revoked.lock().unwrap().contains_key(&family)) })
+    }
+}
+
+#[cfg(feature = "db")]
+pub use surreal::SurrealRefreshTokenStore;
+
+#[cfg(feature = "db")]
+mod surreal {
+    use serde::{Deserialize, Serialize};
+    use surrealdb::{Surreal, engine::remote::ws::Client};
+
+    use super::{RefreshTokenStore, StoreFuture};
+    use crate::{error::Result, services::jwt::now};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Entry {
+        exp: usize,
+    }
+
+    /// A [`RefreshTokenStore`] kept in SurrealDB, shared by every instance of a service.
+    #[derive(Debug, Clone)]
+    pub struct SurrealRefreshTokenStore {
+        db: Surreal<Client>,
+        used_table: String,
+        revoked_table: String,
+    }
+
+    impl SurrealRefreshTokenStore {
+        /// Creates a store using the `jwt_used_refresh_token` and `jwt_revoked_family` tables.
+        pub fn new(db: Surreal<Client>) -> Self {
+            Self::with_tables(db, "jwt_used_refresh_token", "jwt_revoked_family")
+        }
+
+        /// Creates a store using the given tables.
+        pub fn with_tables(db: Surreal<Client>, used_table: &str, revoked_table: &str) -> Self {
+            Self {
+                db,
+                used_table: used_table.to_string(),
+                revoked_table: revoked_table.to_string(),
+            }
+        }
+
+        /// Deletes the expired entries.
+        pub async fn purge_expired(&self) -> Result<()> {
+            self.db
+                .query("DELETE type::table($used) WHERE exp < $now")
+                .query("DELETE type::table($revoked) WHERE exp < $now")
+                .bind(("used", self.used_table.clone()))
+                .bind(("revoked", self.revoked_table.clone()))
+                .bind(("now", now()))
+                .await?
+                .check()?;
+            Ok(())
+        }
+    }
+
+    impl RefreshTokenStore for SurrealRefreshTokenStore {
+        fn mark_used(&self, jti: String, exp: usize) -> StoreFuture<'_, bool> {
+            Box::pin(async move {
+                // A single upsert returning the previous record keeps the check atomic.
+                let mut response = self
+                    .db
+                    .query("UPSERT type::thing($tb, $id) SET exp = $exp RETURN BEFORE")
+                    .bind(("tb", self.used_table.clone()))
+                    .bind(("id", jti))
+                    .bind(("exp", exp))
+                    .await?;
+                let before: Option<Entry> = response.take(0)?;
+                Ok(before.is_none())
+            })
+        }
+
+        fn is_used(&self, jti: String) -> StoreFuture<'_, bool> {
+            Box::pin(async move {
+                let entry: Option<Entry> = self.db.select((self.used_table.as_str(), jti)).await?;
+                Ok(entry.is_some())
+            })
+        }
+
+        fn revoke_family(&self, family: String, exp: usize) -> StoreFuture<'_, ()> {
+            Box::pin(async move {
+                let _: Option<Entry> = self
+                    .db
+                    .upsert((self.revoked_table.as_str(), family))
+                    .content(Entry { exp })
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn is_family_revoked(&self, family: String) -> StoreFuture<'_, bool> {
+            Box::pin(async move {
+                let entry: Option<Entry> = self
+                    .db
+                    .select((self.revoked_table.as_str(), family))
+                    .await?;
+                Ok(entry.is_some())
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryRefreshTokenStore::new();
+        let exp = now() + 60;
+        assert!(store.mark_used("a".to_string(), exp).await.unwrap());
+        assert!(!store.mark_used("a".to_string(), exp).await.unwrap());
+        assert!(store.is_used("a".to_string()).await.unwrap());
+        assert!(!store.is_used("c".to_string()).await.unwrap());
+
+        // Expired entries are dropped.
+        assert!(store.mark_used("b".to_string(), 0).await.unwrap());
+        assert!(store.mark_used("b".to_string(), exp).await.unwrap());
+
+        assert!(!store.is_family_revoked("f".to_string()).await.unwrap());
+        store.revoke_family("f".to_string(), exp).await.unwrap();
+        assert!(store.is_family_revoked("f".to_string()).await.unwrap());
+    }
+}
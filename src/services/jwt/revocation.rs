@@ -0,0 +1,249 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use super::{now, refresh::StoreFuture};
+
+/// Denylist of access tokens revoked before they expire, by `jti`.
+///
+/// `is_revoked` is called on every token validation, which is synchronous, so backends answer
+/// it from memory. Expiry times are UNIX timestamps after which an entry may be dropped.
+pub trait RevocationStore: Send + Sync {
+    /// Denies the token `jti` until `exp`.
+    fn revoke(&self, jti: String, exp: usize) -> StoreFuture<'_, ()>;
+
+    /// Returns `true` if the token `jti` was revoked.
+    fn is_revoked(&self, jti: &str) -> bool;
+}
+
+/// A [`RevocationStore`] kept in memory, dropping entries once the token has expired.
+#[derive(Debug, Default)]
+pub struct InMemoryRevocationStore {
+    revoked: Mutex<HashMap<String, usize>>,
+}
+
+impl InMemoryRevocationStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry.
+    pub fn insert(&self, jti: String, exp: usize) {
+        let now = now();
+        let mut revoked = self.revoked.lock().unwrap();
+        revoked.retain(|_, exp| *exp >= now);
+        revoked.insert(jti, exp);
+    }
+
+    /// Replaces every entry.
+    pub fn replace(&self, entries: HashMap<String, usize>) {
+        *self.revoked.lock().unwrap() = entries;
+    }
+
+    /// Returns the number of entries, expired ones included.
+    pub fn len(&self) -> usize {
+        self.revoked.lock().unwrap().len()
+    }
+
+    /// Returns `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl RevocationStore for InMemoryRevocationStore {
+    fn revoke(&self, jti: String, exp: usize) -> StoreFuture<'_, ()> {
+        self.insert(jti, exp);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn is_revoked(&self, jti: &str) -> bool {
+        self.revoked
+            .lock()
+            .unwrap()
+            .get(jti)
+            .is_some_and(|exp| *exp >= now())
+    }
+}
+
+#[cfg(feature = "db")]
+pub use surreal::SurrealRevocationStore;
+
+#[cfg(feature = "db")]
+mod surreal {
+    use std::{collections::HashMap, sync::Arc, time::Duration};
+
+    use serde::{Deserialize, Serialize};
+    use surrealdb::{Surreal, engine::remote::ws::Client};
+    use tokio::task::JoinHandle;
+
+    use super::{InMemoryRevocationStore, RevocationStore, StoreFuture};
+    use crate::{error::Result, services::jwt::now};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Entry {
+        jti: String,
+        exp: usize,
+    }
+
+    /// A [`RevocationStore`] kept in SurrealDB, shared by every instance of a service.
+    ///
+    /// Lookups are served from a local copy of the table only, refreshed by [`Self::sync`];
+    /// tokens revoked by another instance are denied here from the next sync on. Call
+    /// [`Self::start`] or [`Self::spawn_sync`] after creating the store: otherwise the copy only
+    /// holds the tokens revoked through this instance.
+    pub struct SurrealRevocationStore {
+        db: Surreal<Client>,
+        table: String,
+        cache: InMemoryRevocationStore,
+    }
+
+    impl SurrealRevocationStore {
+        /// Creates a store using the `jwt_revoked_token` table.
+        pub fn new(db: Surreal<Client>) -> Self {
+            Self::with_table(db, "jwt_revoked_token")
+        }
+
+        /// Creates a store using the given table.
+        pub fn with_table(db: Surreal<Client>, table: &str) -> Self {
+            Self {
+                db,
+                table: table.to_string(),
+                cache: InMemoryRevocationStore::new(),
+            }
+        }
+
+        /// Loads the local copy, then syncs it every `interval` like [`Self::spawn_sync`].
+        ///
+        /// Must be called from within a Tokio runtime.
+        pub async fn start(self, interval: Duration) -> Result<(Arc<Self>, JoinHandle<()>)> {
+            let store = Arc::new(self);
+            store.sync().await?;
+            let task = store.spawn_sync(interval);
+            Ok((store, task))
+        }
+
+        /// Deletes the expired entries and reloads the local copy from the table.
+        pub async fn sync(&self) -> Result<()> {
+            let mut response = self
+                .db
+                .query("DELETE type::table($tb) WHERE exp < $now")
+                .query("SELECT jti, exp FROM type::table($tb)")
+                .bind(("tb", self.table.clone()))
+                .bind(("now", now()))
+                .await?;
+            let entries: Vec<Entry> = response.take(1)?;
+            self.cache.replace(
+                entries
+                    .into_iter()
+                    .map(|entry| (entry.jti, entry.exp))
+                    .collect::<HashMap<_, _>>(),
+            );
+            Ok(())
+        }
+
+        /// Syncs the local copy now and then every `interval`.
+        ///
+        /// Required for tokens revoked by other instances to be denied here.
+        ///
+        /// A failed sync keeps the previous copy and is logged. Must be called from within a
+        /// Tokio runtime.
+        pub fn spawn_sync(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+            let store = self.clone();
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(interval);
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = store.sync().await {
+                        eprintln!("Failed to sync revoked tokens: {}", e);
+                    }
+                }
+            })
+        }
+    }
+
+    impl RevocationStore for SurrealRevocationStore {
+        fn revoke(&self, jti: String, exp: usize) -> StoreFuture<'_, ()> {
+            Box::pin(async move {
+                let _: Option<Entry> = self
+                    .db
+                    .upsert((self.table.as_str(), jti.as_str()))
+                    .content(Entry {
+                        jti: jti.clone(),
+                        exp,
+                    })
+                    .await?;
+                self.cache.insert(jti, exp);
+                Ok(())
+            })
+        }
+
+        fn is_revoked(&self, jti: &str) -> bool {
+            self.cache.is_revoked(jti)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryRevocationStore::new();
+        store.revoke("a".to_string(), now() + 60).await.unwrap();
+        store.revoke("b".to_string(), 0).await.unwrap();
+        assert!(store.is_revoked("a"));
+        assert!(!store.is_revoked("b"));
+        assert!(!store.is_revoked("c"));
+
+        // Expired entries are evicted on the next insert.
+        store.revoke("c".to_string(), now() + 60).await.unwrap();
+        assert_eq!(store.len(), 2);
+    }
+
+    #[cfg(feature = "db")]
+    #[tokio::test]
+    #[ignore = "requires a SurrealDB server, see SURREALDB_TEST_ADDR"]
+    async fn test_surreal_store_sees_other_instances() {
+        use std::time::Duration;
+
+        use surrealdb::{Surreal, engine::remote::ws::Ws, opt::auth::Root};
+
+        let addr =
+            std::env::var("SURREALDB_TEST_ADDR").unwrap_or_else(|_| "localhost:8000".to_string());
+        let db = Surreal::new::<Ws>(addr).await.unwrap();
+        db.signin(Root {
+            username: "root",
+            password: "root",
+        })
+        .await
+        .unwrap();
+        db.use_ns("test").use_db("test").await.unwrap();
+
+        let table = format!("jwt_revoked_token_{}", std::process::id());
+        let other = SurrealRevocationStore::with_table(db.clone(), &table);
+        let (store, task) = SurrealRevocationStore::with_table(db.clone(), &table)
+            .start(Duration::from_millis(20))
+            .await
+            .unwrap();
+
+        other.revoke("a".to_string(), now() + 60).await.unwrap();
+        assert!(other.is_revoked("a"));
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !store.is_revoked("a") {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        task.abort();
+
+        // A store started later loads the tokens revoked before.
+        let (late, task) = SurrealRevocationStore::with_table(db, &table)
+            .start(Duration::from_secs(60))
+            .await
+            .unwrap();
+        task.abort();
+        assert!(late.is_revoked("a"));
+    }
+}
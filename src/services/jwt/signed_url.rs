@@ -0,0 +1,403 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use serde::{Serialize, de::DeserializeOwned};
+use sha2::Sha256;
+use url::{Url, form_urlencoded};
+
+use super::{Jwt, TokenError, new_token_id, now};
+use crate::{
+    error::{Error, Result},
+    services::StoreFuture,
+    utils::crypto_util::constant_time_eq,
+};
+
+const EXPIRES: &str = "expires";
+const DATA: &str = "data";
+const NONCE: &str = "nonce";
+const SIGNATURE: &str = "signature";
+
+/// Keeps track of consumed single-use tokens, such as the nonces of single-use signed URLs.
+///
+/// Expiry times are UNIX timestamps after which an entry may be dropped.
+pub trait ConsumedTokenStore: Send + Sync {
+    /// Marks the token `id` as consumed, returning `false` if it already was.
+    fn consume(&self, id: String, exp: usize) -> StoreFuture<'_, bool>;
+}
+
+/// A [`ConsumedTokenStore`] kept in memory, for a single instance or tests.
+#[derive(Debug, Default)]
+pub struct InMemoryConsumedTokenStore {
+    consumed: Mutex<HashMap<String, usize>>,
+}
+
+impl InMemoryConsumedTokenStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ConsumedTokenStore for InMemoryConsumedTokenStore {
+    fn consume(&self, id: String, exp: usize) -> StoreFuture<'_, bool> {
+        let now = now();
+        let mut consumed = self.consumed.lock().unwrap();
+        consumed.retain(|_, exp| *exp >= now);
+        let fresh = consumed.insert(id, exp).is_none();
+        Box::pin(async move { Ok(fresh) })
+    }
+}
+
+#[cfg(feature = "db")]
+pub use surreal::SurrealConsumedTokenStore;
+
+#[cfg(feature = "db")]
+mod surreal {
+    use serde::{Deserialize, Serialize};
+    use surrealdb::{Surreal, engine::remote::ws::Client};
+
+    use super::{ConsumedTokenStore, StoreFuture};
+    use crate::{error::Result, services::jwt::now};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Entry {
+        exp: usize,
+    }
+
+    /// A [`ConsumedTokenStore`] kept in SurrealDB, shared by every instance of a service.
+    #[derive(Debug, Clone)]
+    pub struct SurrealConsumedTokenStore {
+        db: Surreal<Client>,
+        table: String,
+    }
+
+    impl SurrealConsumedTokenStore {
+        /// Creates a store using the `consumed_token` table.
+        pub fn new(db: Surreal<Client>) -> Self {
+            Self::with_table(db, "consumed_token")
+        }
+
+        /// Creates a store using the given table.
+        pub fn with_table(db: Surreal<Client>, table: &str) -> Self {
+            Self {
+                db,
+                table: table.to_string(),
+            }
+        }
+
+        /// Deletes the expired entries.
+        pub async fn purge_expired(&self) -> Result<()> {
+            self.db
+                .query("DELETE type::table($tb) WHERE exp < $now")
+                .bind(("tb", self.table.clone()))
+                .bind(("now", now()))
+                .await?
+                .check()?;
+            Ok(())
+        }
+    }
+
+    impl ConsumedTokenStore for SurrealConsumedTokenStore {
+        fn consume(&self, id: String, exp: usize) -> StoreFuture<'_, bool> {
+            Box::pin(async move {
+                // A single upsert returning the previous record keeps the check atomic.
+                let mut response = self
+                    .db
+                    .query("UPSERT type::thing($tb, $id) SET exp = $exp RETURN BEFORE")
+                    .bind(("tb", self.table.clone()))
+                    .bind(("id", id))
+                    .bind(("exp", exp))
+                    .await?;
+                let before: Option<Entry> = response.take(0)?;
+                Ok(before.is_none())
+            })
+        }
+    }
+}
+
+impl Jwt {
+    /// Signs `url` so that it can be verified with [`Jwt::verify_signed_url`] until `ttl`
+    /// seconds from now, e.g. for email verification or download links.
+    ///
+    /// The path and query are signed with a key derived from `refresh_secret`, so URLs can only
+    /// be signed and verified by instances that issue tokens. `claims` are embedded in the URL
+    /// readable by anyone, but cannot be altered.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to sign, e.g. built with `utils::request::parse_url`.
+    /// * `ttl` - The lifetime of the URL in seconds.
+    /// * `claims` - The data to embed, `&()` for none.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the signed URL, or an `Error`.
+    pub fn sign_url<C>(&self, url: Url, ttl: usize, claims: &C) -> Result<Url>
+    where
+        C: Serialize,
+    {
+        self.sign(url, ttl, claims, None)
+    }
+
+    /// Verifies a URL signed with [`Jwt::sign_url`].
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The absolute URL, or only its path and query, e.g. the request URI of a handler.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the embedded claims, or an `Error` if the URL was altered, is not
+    ///   signed or has expired.
+    pub fn verify_signed_url<C>(&self, url: &str) -> Result<C>
+    where
+        C: DeserializeOwned,
+    {
+        self.verify(url).map(|(claims, _)| claims)
+    }
+
+    /// Like [`Jwt::sign_url`], but the URL can only be verified once, e.g. for password reset
+    /// links.
+    ///
+    /// Requires a store set with [`Jwt::with_consumed_token_store`].
+    pub fn sign_url_once<C>(&self, url: Url, ttl: usize, claims: &C) -> Result<Url>
+    where
+        C: Serialize,
+    {
+        self.sign(url, ttl, claims, Some(new_token_id()))
+    }
+
+    /// Verifies a URL signed with [`Jwt::sign_url_once`], consuming it.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The absolute URL, or only its path and query.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the embedded claims, or an `Error` if the URL is invalid or was
+    ///   already used.
+    pub async fn verify_signed_url_once<C>(&self, url: &str) -> Result<C>
+    where
+        C: DeserializeOwned,
+    {
+        let store = self
+            .consumed_store
+            .as_ref()
+            .ok_or_else(|| Error::SystemError("no consumed token store configured".to_string()))?;
+        let (claims, signed) = self.verify(url)?;
+        let nonce = signed
+            .nonce
+            .ok_or_else(|| Error::AuthError("signed url is not single-use".to_string()))?;
+        if !store.consume(nonce, signed.exp).await? {
+            return Err(Error::AuthError("signed url already used".to_string()));
+        }
+        Ok(claims)
+    }
+
+    fn sign<C>(&self, mut url: Url, ttl: usize, claims: &C, nonce: Option<String>) -> Result<Url>
+    where
+        C: Serialize,
+    {
+        let key = self.url_key()?;
+        if url
+            .query_pairs()
+            .any(|(name, _)| [EXPIRES, DATA, NONCE, SIGNATURE].contains(&name.as_ref()))
+        {
+            return Err(Error::ErrorMessage(
+                "url already has signature parameters".to_string(),
+            ));
+        }
+        let (_, exp) = self.generate_timestamps(ttl);
+        let data = serde_json::to_value(claims)?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair(EXPIRES, &exp.to_string());
+            if !data.is_null() {
+                query.append_pair(DATA, &URL_SAFE_NO_PAD.encode(data.to_string()));
+            }
+            if let Some(nonce) = &nonce {
+                query.append_pair(NONCE, nonce);
+            }
+        }
+        let (message, _) = canonical(&url);
+        url.query_pairs_mut()
+            .append_pair(SIGNATURE, &signature(key, &message));
+        Ok(url)
+    }
+
+    fn verify<C>(&self, url: &str) -> Result<(C, Signed)>
+    where
+        C: DeserializeOwned,
+    {
+        self.verify_at(url, now())
+    }
+
+    /// Verifies `url` at `time`, a UNIX timestamp.
+    fn verify_at<C>(&self, url: &str, time: usize) -> Result<(C, Signed)>
+    where
+        C: DeserializeOwned,
+    {
+        let key = self.url_key()?;
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(url::ParseError::RelativeUrlWithoutBase) => Url::parse("http://localhost")
+                .and_then(|base| base.join(url))
+                .map_err(|_| TokenError::Malformed)?,
+            Err(_) => return Err(TokenError::Malformed.into()),
+        };
+        let (message, provided) = canonical(&url);
+        let provided = provided.ok_or(TokenError::InvalidSignature)?;
+        if !constant_time_eq(signature(key, &message).as_bytes(), provided.as_bytes()) {
+            return Err(TokenError::InvalidSignature.into());
+        }
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let exp: usize = param(EXPIRES)
+            .and_then(|exp| exp.parse().ok())
+            .ok_or(TokenError::Malformed)?;
+        if exp < time {
+            return Err(TokenError::Expired.into());
+        }
+        let data = match param(DATA) {
+            Some(data) => URL_SAFE_NO_PAD
+                .decode(data)
+                .map_err(|_| TokenError::Malformed)?,
+            None => b"null".to_vec(),
+        };
+        let claims = serde_json::from_slice(&data).map_err(|_| TokenError::Malformed)?;
+        let signed = Signed {
+            exp,
+            nonce: param(NONCE),
+        };
+        Ok((claims, signed))
+    }
+
+    fn url_key(&self) -> Result<&[u8]> {
+        self.url_key
+            .as_ref()
+            .map(|key| key.expose_secret().as_slice())
+            .ok_or_else(|| Error::SystemError("no key configured for signed urls".to_string()))
+    }
+}
+
+/// Registered parameters of a verified URL.
+struct Signed {
+    exp: usize,
+    nonce: Option<String>,
+}
+
+/// Derives the key signing URLs from `secret`, keeping signatures of URLs and of tokens apart.
+pub(super) fn derive_url_key(secret: &[u8]) -> Vec<u8> {
+    mac(secret, b"service_utils_rs signed url")
+}
+
+/// Returns the signed message of `url`, its path and query without the signature, and the
+/// signature it carries.
+fn canonical(url: &Url) -> (String, Option<String>) {
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    let mut provided = None;
+    for (name, value) in url.query_pairs() {
+        if name == SIGNATURE {
+            provided = Some(value.into_owned());
+        } else {
+            query.append_pair(&name, &value);
+        }
+    }
+    (format!("{}?{}", url.path(), query.finish()), provided)
+}
+
+fn signature(key: &[u8], message: &str) -> String {
+    URL_SAFE_NO_PAD.encode(mac(key, message.as_bytes()))
+}
+
+fn mac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{services::jwt::JwtCfg, utils::ConfigDuration};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Download {
+        file_id: u32,
+    }
+
+    fn setup_jwt() -> Jwt {
+        Jwt::new(JwtCfg {
+            access_secret: "access_secret".into(),
+            refresh_secret: "refresh_secret".into(),
+            audience: "test_audience".to_string(),
+            access_token_duration: ConfigDuration::from_secs(3600),
+            refresh_token_duration: ConfigDuration::from_secs(86400),
+            access_key_validate_exp: true,
+            refresh_key_validate_exp: true,
+            ..Default::default()
+        })
+        .with_consumed_token_store(Arc::new(InMemoryConsumedTokenStore::new()))
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let jwt = setup_jwt();
+        let url = Url::parse("https://files.example.com/download?name=report.pdf").unwrap();
+        let signed = jwt.sign_url(url, 600, &Download { file_id: 42 }).unwrap();
+        let claims: Download = jwt.verify_signed_url(signed.as_str()).unwrap();
+        assert_eq!(claims, Download { file_id: 42 });
+
+        // Handlers only see the path and query.
+        let relative = &signed.as_str()["https://files.example.com".len() ..];
+        assert!(jwt.verify_signed_url::<Download>(relative).is_ok());
+
+        let tampered = signed.as_str().replace("report.pdf", "secret.pdf");
+        assert!(matches!(
+            jwt.verify_signed_url::<Download>(&tampered),
+            Err(Error::TokenError(TokenError::InvalidSignature))
+        ));
+        let unsigned = "https://files.example.com/download?name=report.pdf";
+        assert!(jwt.verify_signed_url::<Download>(unsigned).is_err());
+
+        let expired = jwt
+            .sign_url(Url::parse("https://example.com/verify").unwrap(), 0, &())
+            .unwrap();
+        assert!(matches!(
+            jwt.verify_at::<()>(expired.as_str(), now() + 1),
+            Err(Error::TokenError(TokenError::Expired))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_single_use() {
+        let jwt = setup_jwt();
+        let url = Url::parse("https://example.com/reset").unwrap();
+        let signed = jwt.sign_url_once(url.clone(), 600, &()).unwrap();
+        jwt.verify_signed_url_once::<()>(signed.as_str())
+            .await
+            .unwrap();
+        let err = jwt
+            .verify_signed_url_once::<()>(signed.as_str())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "auth error: signed url already used");
+
+        let reusable = jwt.sign_url(url, 600, &()).unwrap();
+        assert!(
+            jwt.verify_signed_url_once::<()>(reusable.as_str())
+                .await
+                .is_err()
+        );
+    }
+}
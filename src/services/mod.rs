@@ -9,3 +9,23 @@ pub mod websocket;
 
 #[cfg(feature = "http")]
 pub mod http;
+
+#[cfg(feature = "api_key")]
+pub mod api_key;
+
+#[cfg(feature = "password")]
+pub mod password;
+
+#[cfg(feature = "otp")]
+pub mod otp;
+
+pub mod grants;
+pub mod settings;
+
+use std::{future::Future, pin::Pin};
+
+pub use grants::Grants;
+pub use settings::ServiceSettings;
+
+/// Boxed future returned by the methods of pluggable stores.
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = crate::error::Result<T>> + Send + 'a>>;
@@ -0,0 +1,480 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::Deserialize;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use url::form_urlencoded::byte_serialize;
+
+use crate::{
+    error::{Error, Result},
+    utils::{
+        ConfigDuration, Validate, ValidationErrors,
+        config_schema::{ConfigField, ConfigSchema, FieldKind},
+        crypto_util::constant_time_eq,
+    },
+};
+
+/// HMAC algorithm of one-time passwords. Most authenticator apps only support `SHA1`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum OtpAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl OtpAlgorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            OtpAlgorithm::Sha1 => "SHA1",
+            OtpAlgorithm::Sha256 => "SHA256",
+            OtpAlgorithm::Sha512 => "SHA512",
+        }
+    }
+}
+
+/// Struct representing the one-time password parameters.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OtpCfg {
+    /// Issuer shown by authenticator apps, usually the service name.
+    #[serde(default)]
+    pub issuer: String,
+    #[serde(default)]
+    pub algorithm: OtpAlgorithm,
+    /// Number of digits of a code, 6 by default.
+    #[serde(default = "default_digits")]
+    pub digits: u32,
+    /// Lifetime of a TOTP code, 30 seconds by default.
+    #[serde(default = "default_step")]
+    pub step: ConfigDuration,
+    /// Number of steps (TOTP) or counters (HOTP) accepted after, and for TOTP before, the
+    /// current one, to absorb clock drift and unused codes. 1 by default.
+    #[serde(default = "default_skew")]
+    pub skew: u32,
+}
+
+fn default_digits() -> u32 {
+    6
+}
+
+fn default_step() -> ConfigDuration {
+    ConfigDuration::from_secs(30)
+}
+
+fn default_skew() -> u32 {
+    1
+}
+
+impl Default for OtpCfg {
+    fn default() -> Self {
+        Self {
+            issuer: String::new(),
+            algorithm: OtpAlgorithm::default(),
+            digits: default_digits(),
+            step: default_step(),
+            skew: default_skew(),
+        }
+    }
+}
+
+impl ConfigSchema for OtpCfg {
+    fn description() -> &'static str {
+        "One-time passwords (TOTP/HOTP) for two-factor authentication."
+    }
+
+    fn fields() -> Vec<ConfigField> {
+        vec![
+            ConfigField::optional(
+                "issuer",
+                FieldKind::String,
+                "Issuer shown by authenticator apps.",
+                "\"My Service\"",
+            ),
+            ConfigField::optional(
+                "algorithm",
+                FieldKind::String,
+                "HMAC algorithm: SHA1, SHA256 or SHA512.",
+                "\"SHA1\"",
+            ),
+            ConfigField::optional("digits", FieldKind::Integer, "Digits of a code.", "6"),
+            ConfigField::optional(
+                "step",
+                FieldKind::Duration,
+                "Lifetime of a TOTP code.",
+                "\"30s\"",
+            ),
+            ConfigField::optional(
+                "skew",
+                FieldKind::Integer,
+                "Steps or counters accepted around the current one.",
+                "1",
+            ),
+        ]
+    }
+}
+
+impl Validate for OtpCfg {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        errors.ensure(
+            (6 ..= 9).contains(&self.digits),
+            "digits",
+            "must be between 6 and 9",
+        );
+        errors.ensure(!self.step.is_zero(), "step", "must be greater than 0");
+        errors.ensure(!self.issuer.contains(':'), "issuer", "must not contain ':'");
+    }
+}
+
+/// Generates and verifies HOTP (RFC 4226) and TOTP (RFC 6238) codes.
+///
+/// Secrets are exchanged base32-encoded, the format authenticator apps expect. Unlike passwords
+/// they cannot be hashed, as codes are derived from them: keep them encrypted at rest.
+#[derive(Debug, Clone)]
+pub struct Otp {
+    cfg: OtpCfg,
+}
+
+impl Otp {
+    /// Creates a generator from the given configuration.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the generator, or an `Error::ValidationError` if the configuration
+    ///   is invalid.
+    pub fn new(cfg: OtpCfg) -> Result<Self> {
+        cfg.validate_all()?;
+        Ok(Self { cfg })
+    }
+
+    /// Generates a random 160-bit secret, base32-encoded.
+    pub fn generate_secret() -> String {
+        base32_encode(&rand::random::<[u8; 20]>())
+    }
+
+    /// Returns the HOTP code of `secret` for `counter`.
+    pub fn hotp(&self, secret: &str, counter: u64) -> Result<String> {
+        Ok(self.code(&decode_secret(secret)?, counter))
+    }
+
+    /// Verifies an HOTP code against `counter` and the `skew` counters after it.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the counter to store for the next verification, or `None` if the
+    ///   code does not match.
+    pub fn verify_hotp(&self, secret: &str, code: &str, counter: u64) -> Result<Option<u64>> {
+        let secret = decode_secret(secret)?;
+        let next = (counter ..= counter.saturating_add(self.cfg.skew as u64))
+            .find(|&counter| {
+                constant_time_eq(self.code(&secret, counter).as_bytes(), code.as_bytes())
+            })
+            .and_then(|counter| counter.checked_add(1));
+        Ok(next)
+    }
+
+    /// Returns the current TOTP code of `secret`.
+    pub fn totp(&self, secret: &str) -> Result<String> {
+        self.totp_at(secret, now())
+    }
+
+    /// Returns the TOTP code of `secret` at `time`, a UNIX timestamp.
+    pub fn totp_at(&self, secret: &str, time: u64) -> Result<String> {
+        self.hotp(secret, time / self.step())
+    }
+
+    /// Verifies a TOTP code, accepting `skew` steps before and after the current one.
+    ///
+    /// A code stays valid for several steps, so store the returned step and pass it as
+    /// `last_step` on the next verification to reject replays of codes already used.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - The base32-encoded secret.
+    /// * `code` - The code to verify.
+    /// * `last_step` - The step returned by the last successful verification, if any.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the matched step to store, or `None` if the code does not match or
+    ///   its step is not after `last_step`.
+    pub fn verify_totp(
+        &self,
+        secret: &str,
+        code: &str,
+        last_step: Option<u64>,
+    ) -> Result<Option<u64>> {
+        self.verify_totp_at(secret, code, last_step, now())
+    }
+
+    /// Verifies a TOTP code at `time`, a UNIX timestamp, like [`Otp::verify_totp`].
+    pub fn verify_totp_at(
+        &self,
+        secret: &str,
+        code: &str,
+        last_step: Option<u64>,
+        time: u64,
+    ) -> Result<Option<u64>> {
+        let secret = decode_secret(secret)?;
+        let step = time / self.step();
+        let skew = self.cfg.skew as u64;
+        let first = match last_step {
+            Some(last_step) => step.saturating_sub(skew).max(last_step.saturating_add(1)),
+            None => step.saturating_sub(skew),
+        };
+        let matched = (first ..= step.saturating_add(skew))
+            .find(|&step| constant_time_eq(self.code(&secret, step).as_bytes(), code.as_bytes()));
+        Ok(matched)
+    }
+
+    /// Builds the `otpauth://totp/` URI that authenticator apps import, usually shown as a QR
+    /// code.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - The base32-encoded secret.
+    /// * `account` - The account name shown next to the issuer, e.g. an email address.
+    pub fn provisioning_uri(&self, secret: &str, account: &str) -> String {
+        let label = if self.cfg.issuer.is_empty() {
+            encode(account)
+        } else {
+            format!("{}:{}", encode(&self.cfg.issuer), encode(account))
+        };
+        let mut uri = format!(
+            "otpauth://totp/{}?secret={}&algorithm={}&digits={}&period={}",
+            label,
+            secret,
+            self.cfg.algorithm.name(),
+            self.cfg.digits,
+            self.step()
+        );
+        if !self.cfg.issuer.is_empty() {
+            uri.push_str("&issuer=");
+            uri.push_str(&encode(&self.cfg.issuer));
+        }
+        uri
+    }
+
+    fn step(&self) -> u64 {
+        self.cfg.step.as_secs().max(1)
+    }
+
+    fn code(&self, secret: &[u8], counter: u64) -> String {
+        let counter = counter.to_be_bytes();
+        let hash = match self.cfg.algorithm {
+            OtpAlgorithm::Sha1 => mac::<Hmac<Sha1>>(secret, &counter),
+            OtpAlgorithm::Sha256 => mac::<Hmac<Sha256>>(secret, &counter),
+            OtpAlgorithm::Sha512 => mac::<Hmac<Sha512>>(secret, &counter),
+        };
+        // Dynamic truncation, RFC 4226 section 5.3.
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        let digits = self.cfg.digits as usize;
+        format!(
+            "{:0width$}",
+            binary as u64 % 10u64.pow(digits as u32),
+            width = digits
+        )
+    }
+}
+
+/// Generates `count` single-use recovery codes such as `k7m2p-x9qfr`, for users who lost their
+/// authenticator.
+///
+/// Hand them to the user once and store only their hashes, e.g. with `services::password`.
+pub fn generate_recovery_codes(count: usize) -> Vec<String> {
+    // Without 0, 1, i, l and o, which are easily confused.
+    const ALPHABET: &[u8] = b"23456789abcdefghjkmnpqrstuvwxyz";
+    let mut rng = rand::rng();
+    (0 .. count)
+        .map(|_| {
+            let mut code = String::with_capacity(11);
+            for i in 0 .. 10 {
+                if i == 5 {
+                    code.push('-');
+                }
+                code.push(ALPHABET[rng.random_range(0 .. ALPHABET.len())] as char);
+            }
+            code
+        })
+        .collect()
+}
+
+fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(msg);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn decode_secret(secret: &str) -> Result<Vec<u8>> {
+    base32_decode(secret)
+        .filter(|secret| !secret.is_empty())
+        .ok_or_else(|| Error::SystemError("invalid OTP secret".to_string()))
+}
+
+fn encode(s: &str) -> String {
+    byte_serialize(s.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+fn now() -> u64 {
+    Utc::now().timestamp() as u64
+}
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encodes bytes as unpadded base32 (RFC 4648).
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+/// Decodes base32, ignoring case, spaces and padding.
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in s.bytes().filter(|c| !matches!(c, b' ' | b'=')) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn otp(algorithm: OtpAlgorithm, digits: u32) -> Otp {
+        Otp::new(OtpCfg {
+            algorithm,
+            digits,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rfc_vectors() {
+        let secret = base32_encode(b"12345678901234567890");
+        let hotp = otp(OtpAlgorithm::Sha1, 6);
+        let codes: Vec<String> = (0 .. 3).map(|c| hotp.hotp(&secret, c).unwrap()).collect();
+        assert_eq!(codes, ["755224", "287082", "359152"]);
+
+        let totp = otp(OtpAlgorithm::Sha1, 8);
+        assert_eq!(totp.totp_at(&secret, 59).unwrap(), "94287082");
+        assert_eq!(totp.totp_at(&secret, 1111111109).unwrap(), "07081804");
+        let secret = base32_encode(b"12345678901234567890123456789012");
+        let totp = otp(OtpAlgorithm::Sha256, 8);
+        assert_eq!(totp.totp_at(&secret, 59).unwrap(), "46119246");
+        let secret =
+            base32_encode(b"1234567890123456789012345678901234567890123456789012345678901234");
+        let totp = otp(OtpAlgorithm::Sha512, 8);
+        assert_eq!(totp.totp_at(&secret, 59).unwrap(), "90693936");
+    }
+
+    #[test]
+    fn test_verify() {
+        let otp = Otp::new(OtpCfg::default()).unwrap();
+        let secret = Otp::generate_secret();
+        assert_eq!(secret.len(), 32);
+
+        let verify = |code: &str, time| otp.verify_totp_at(&secret, code, None, time).unwrap();
+        let code = otp.totp_at(&secret, 1000).unwrap();
+        assert_eq!(verify(&code, 1000), Some(33));
+        assert_eq!(verify(&code, 1030), Some(33));
+        assert_eq!(verify(&code, 1090), None);
+
+        let code = otp.hotp(&secret, 5).unwrap();
+        assert_eq!(otp.verify_hotp(&secret, &code, 4).unwrap(), Some(6));
+        assert_eq!(otp.verify_hotp(&secret, &code, 6).unwrap(), None);
+        assert_eq!(otp.verify_totp(&secret, "12345", None).unwrap(), None);
+        assert!(otp.hotp("not base32!", 0).is_err());
+
+        // The last counter has no next one to store.
+        let code = otp.hotp(&secret, u64::MAX).unwrap();
+        assert_eq!(otp.verify_hotp(&secret, &code, u64::MAX).unwrap(), None);
+    }
+
+    #[test]
+    fn test_new_validates_cfg() {
+        let err = Otp::new(OtpCfg {
+            digits: 20,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(matches!(err, Error::ValidationError(_)));
+    }
+
+    #[test]
+    fn test_totp_replay() {
+        let otp = Otp::new(OtpCfg::default()).unwrap();
+        let secret = Otp::generate_secret();
+        let verify = |code: &str, last_step, time| {
+            otp.verify_totp_at(&secret, code, last_step, time).unwrap()
+        };
+        let code = otp.totp_at(&secret, 1000).unwrap();
+        let step = verify(&code, None, 1000);
+        assert_eq!(step, Some(33));
+
+        // The same code is rejected for the rest of its validity, the next one is accepted.
+        assert_eq!(verify(&code, step, 1000), None);
+        assert_eq!(verify(&code, step, 1020), None);
+        let next = otp.totp_at(&secret, 1020).unwrap();
+        assert_eq!(verify(&next, step, 1020), Some(34));
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let otp = Otp::new(OtpCfg {
+            issuer: "My Service".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            otp.provisioning_uri("JBSWY3DPEHPK3PXP", "jane@example.com"),
+            "otpauth://totp/My%20Service:jane%40example.com?secret=JBSWY3DPEHPK3PXP&\
+             algorithm=SHA1&digits=6&period=30&issuer=My%20Service"
+        );
+    }
+
+    #[test]
+    fn test_base32_and_recovery_codes() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("mzxw6ytboi======").unwrap(), b"foobar");
+        assert_eq!(base32_decode("MZXW 6YTB OI").unwrap(), b"foobar");
+
+        let codes = generate_recovery_codes(10);
+        assert_eq!(codes.len(), 10);
+        assert!(
+            codes
+                .iter()
+                .all(|code| code.len() == 11 && &code[5 .. 6] == "-")
+        );
+    }
+}
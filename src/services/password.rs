@@ -0,0 +1,255 @@
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+    password_hash::{self, SaltString},
+};
+use serde::Deserialize;
+
+pub use crate::utils::crypto_util::constant_time_eq;
+use crate::{
+    error::{Error, Result},
+    utils::{
+        Validate, ValidationErrors,
+        config_schema::{ConfigField, ConfigSchema, FieldKind},
+    },
+};
+
+/// Struct representing the password hashing parameters.
+///
+/// The defaults follow the OWASP recommendation for Argon2id: 19 MiB of memory, 2 iterations
+/// and 1 degree of parallelism. Raising them makes every login slower, and makes existing
+/// hashes report [`Verification::NeedsRehash`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct PasswordCfg {
+    /// Memory cost in KiB.
+    #[serde(default = "default_memory_kib")]
+    pub memory_kib: u32,
+    /// Number of passes over the memory.
+    #[serde(default = "default_iterations")]
+    pub iterations: u32,
+    /// Number of lanes.
+    #[serde(default = "default_parallelism")]
+    pub parallelism: u32,
+}
+
+fn default_memory_kib() -> u32 {
+    19 * 1024
+}
+
+fn default_iterations() -> u32 {
+    2
+}
+
+fn default_parallelism() -> u32 {
+    1
+}
+
+impl Default for PasswordCfg {
+    fn default() -> Self {
+        Self {
+            memory_kib: default_memory_kib(),
+            iterations: default_iterations(),
+            parallelism: default_parallelism(),
+        }
+    }
+}
+
+impl PasswordCfg {
+    fn params(&self) -> Result<Params> {
+        Params::new(self.memory_kib, self.iterations, self.parallelism, None).map_err(|e| {
+            Error::ConfigError(config::ConfigError::Message(format!(
+                "invalid password parameters: {}",
+                e
+            )))
+        })
+    }
+}
+
+impl ConfigSchema for PasswordCfg {
+    fn description() -> &'static str {
+        "Password hashing (Argon2id)."
+    }
+
+    fn fields() -> Vec<ConfigField> {
+        vec![
+            ConfigField::optional(
+                "memory_kib",
+                FieldKind::Integer,
+                "Memory cost in KiB.",
+                "19456",
+            ),
+            ConfigField::optional(
+                "iterations",
+                FieldKind::Integer,
+                "Number of passes over the memory.",
+                "2",
+            ),
+            ConfigField::optional("parallelism", FieldKind::Integer, "Number of lanes.", "1"),
+        ]
+    }
+}
+
+impl Validate for PasswordCfg {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        errors.ensure(
+            self.iterations >= Params::MIN_T_COST,
+            "iterations",
+            "must be greater than 0",
+        );
+        errors.ensure(
+            (Params::MIN_P_COST ..= Params::MAX_P_COST).contains(&self.parallelism),
+            "parallelism",
+            format!("must be between 1 and {}", Params::MAX_P_COST),
+        );
+        errors.ensure(
+            self.memory_kib >= 8 * self.parallelism,
+            "memory_kib",
+            "must be at least 8 times parallelism",
+        );
+    }
+}
+
+/// Outcome of [`Passwords::verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    /// The password does not match.
+    Invalid,
+    /// The password matches.
+    Valid,
+    /// The password matches, but the hash uses outdated parameters. Store
+    /// [`Passwords::hash`] of the password in its place.
+    NeedsRehash,
+}
+
+impl Verification {
+    /// Returns `true` if the password matches.
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, Verification::Invalid)
+    }
+}
+
+/// Hashes and verifies passwords with Argon2id, storing hashes as PHC strings such as
+/// `$argon2id$v=19$m=19456,t=2,p=1$<salt>$<hash>`.
+///
+/// Hashing is deliberately slow; from async code, call it through
+/// `tokio::task::spawn_blocking`.
+#[derive(Clone)]
+pub struct Passwords {
+    argon2: Argon2<'static>,
+}
+
+impl Passwords {
+    /// Creates a hasher from the given configuration.
+    pub fn new(cfg: &PasswordCfg) -> Result<Self> {
+        Ok(Self {
+            argon2: Argon2::new(Algorithm::Argon2id, Version::V0x13, cfg.params()?),
+        })
+    }
+
+    /// Hashes `password` with a random salt.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the PHC string to store, or an `Error`.
+    pub fn hash(&self, password: &str) -> Result<String> {
+        let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>()).map_err(hash_error)?;
+        let hash = self
+            .argon2
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(hash_error)?;
+        Ok(hash.to_string())
+    }
+
+    /// Verifies `password` against a stored PHC string, using the parameters recorded in it.
+    ///
+    /// # Returns
+    ///
+    /// * A `Result` containing the [`Verification`], or an `Error` if the stored hash cannot be
+    ///   parsed.
+    pub fn verify(&self, password: &str, hash: &str) -> Result<Verification> {
+        let parsed = PasswordHash::new(hash).map_err(hash_error)?;
+        match self.argon2.verify_password(password.as_bytes(), &parsed) {
+            Ok(()) if self.is_current(&parsed) => Ok(Verification::Valid),
+            Ok(()) => Ok(Verification::NeedsRehash),
+            Err(password_hash::Error::Password) => Ok(Verification::Invalid),
+            Err(e) => Err(hash_error(e)),
+        }
+    }
+
+    /// Returns `true` if a stored PHC string was not produced with the current algorithm and
+    /// parameters, or cannot be parsed.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        PasswordHash::new(hash).map_or(true, |parsed| !self.is_current(&parsed))
+    }
+
+    fn is_current(&self, hash: &PasswordHash) -> bool {
+        hash.algorithm == Algorithm::Argon2id.ident()
+            && hash.version == Some(Version::V0x13.into())
+            && Params::try_from(hash).is_ok_and(|params| {
+                let current = self.argon2.params();
+                params.m_cost() == current.m_cost()
+                    && params.t_cost() == current.t_cost()
+                    && params.p_cost() == current.p_cost()
+            })
+    }
+}
+
+fn hash_error(e: password_hash::Error) -> Error {
+    Error::SystemError(format!("password hash error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg(memory_kib: u32) -> PasswordCfg {
+        PasswordCfg {
+            memory_kib,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    #[test]
+    fn test_hash_and_verify() {
+        let passwords = Passwords::new(&cfg(64)).unwrap();
+        let hash = passwords.hash("hunter2").unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+        assert_ne!(passwords.hash("hunter2").unwrap(), hash);
+
+        assert_eq!(
+            passwords.verify("hunter2", &hash).unwrap(),
+            Verification::Valid
+        );
+        assert_eq!(
+            passwords.verify("hunter3", &hash).unwrap(),
+            Verification::Invalid
+        );
+        assert!(!passwords.needs_rehash(&hash));
+        assert!(passwords.verify("hunter2", "not a hash").is_err());
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        let old = Passwords::new(&cfg(64)).unwrap();
+        let hash = old.hash("hunter2").unwrap();
+
+        let passwords = Passwords::new(&cfg(128)).unwrap();
+        assert!(passwords.needs_rehash(&hash));
+        assert_eq!(
+            passwords.verify("hunter2", &hash).unwrap(),
+            Verification::NeedsRehash
+        );
+        assert_eq!(
+            passwords.verify("hunter3", &hash).unwrap(),
+            Verification::Invalid
+        );
+        assert!(passwords.needs_rehash("garbage"));
+    }
+
+    #[test]
+    fn test_validate_cfg() {
+        assert!(PasswordCfg::default().validate_all().is_ok());
+        assert!(cfg(4).validate_all().is_err());
+        assert!(Passwords::new(&cfg(4)).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use serde::Deserialize;
+
+#[cfg(feature = "db")]
+use super::db::SurrealdbCfg;
+#[cfg(feature = "http")]
+use super::http::HttpCfg;
+#[cfg(feature = "jwt")]
+use super::jwt::JwtCfg;
+#[cfg(feature = "otp")]
+use super::otp::OtpCfg;
+#[cfg(feature = "password")]
+use super::password::PasswordCfg;
+#[cfg(feature = "websocket")]
+use super::websocket::server::WebsocketCfg;
+use crate::{
+    error::Result,
+    utils::{
+        Validate, ValidationErrors,
+        config_schema::{self, ConfigSection},
+        load_settings,
+    },
+};
+
+/// Configuration sections of every built-in service enabled through cargo features.
+///
+/// Each section is read from the table named after its field:
+///
+/// ```toml
+/// [jwt]        # feature "jwt"
+/// [surrealdb]  # feature "db"
+/// [websocket]  # feature "websocket"
+/// [http]       # feature "http"
+/// [password]   # feature "password", optional
+/// [otp]        # feature "otp", optional
+/// ```
+///
+/// Applications can flatten it into their own settings:
+///
+/// ```no_run
+/// use serde::Deserialize;
+/// use service_utils_rs::services::ServiceSettings;
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Settings {
+///     #[serde(flatten)]
+///     services: ServiceSettings,
+///     app_name: String,
+/// }
+/// ```
+///
+/// Flattened fields are buffered by serde, so numeric values overridden through environment
+/// variables are not converted from strings. Nest the struct under a named field instead if such
+/// overrides are needed.
+#[derive(Debug, Deserialize)]
+pub struct ServiceSettings {
+    #[cfg(feature = "jwt")]
+    pub jwt: JwtCfg,
+    #[cfg(feature = "db")]
+    pub surrealdb: SurrealdbCfg,
+    #[cfg(feature = "websocket")]
+    pub websocket: WebsocketCfg,
+    #[cfg(feature = "http")]
+    pub http: HttpCfg,
+    #[cfg(feature = "password")]
+    #[serde(default)]
+    pub password: PasswordCfg,
+    #[cfg(feature = "otp")]
+    #[serde(default)]
+    pub otp: OtpCfg,
+}
+
+impl ServiceSettings {
+    /// Loads the service settings from a config file.
+    pub fn load(config_path: &str) -> Result<Self> {
+        load_settings(config_path)
+    }
+
+    /// Describes the sections of every enabled service.
+    #[allow(clippy::vec_init_then_push)]
+    pub fn sections() -> Vec<ConfigSection> {
+        #[allow(unused_mut)]
+        let mut sections = Vec::new();
+        #[cfg(feature = "jwt")]
+        sections.push(ConfigSection::of::<JwtCfg>("jwt"));
+        #[cfg(feature = "db")]
+        sections.push(ConfigSection::of::<SurrealdbCfg>("surrealdb"));
+        #[cfg(feature = "websocket")]
+        sections.push(ConfigSection::of::<WebsocketCfg>("websocket"));
+        #[cfg(feature = "http")]
+        sections.push(ConfigSection::of::<HttpCfg>("http"));
+        #[cfg(feature = "password")]
+        sections.push(ConfigSection::of::<PasswordCfg>("password").optional());
+        #[cfg(feature = "otp")]
+        sections.push(ConfigSection::of::<OtpCfg>("otp").optional());
+        sections
+    }
+
+    /// Renders a commented sample TOML file covering every enabled service.
+    pub fn sample_toml() -> String {
+        config_schema::sample_toml(&Self::sections())
+    }
+
+    /// Builds a JSON Schema covering every enabled service.
+    pub fn json_schema() -> serde_json::Value {
+        config_schema::json_schema(&Self::sections())
+    }
+}
+
+impl Validate for ServiceSettings {
+    #[allow(unused_variables)]
+    fn validate(&self, errors: &mut ValidationErrors) {
+        #[cfg(feature = "jwt")]
+        errors.nested("jwt", &self.jwt);
+        #[cfg(feature = "db")]
+        errors.nested("surrealdb", &self.surrealdb);
+        #[cfg(feature = "websocket")]
+        errors.nested("websocket", &self.websocket);
+        #[cfg(feature = "http")]
+        errors.nested("http", &self.http);
+        #[cfg(feature = "password")]
+        errors.nested("password", &self.password);
+        #[cfg(feature = "otp")]
+        errors.nested("otp", &self.otp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "http")]
+    use crate::error::Error;
+
+    #[derive(Debug, Deserialize)]
+    struct AppSettings {
+        #[cfg(any(feature = "jwt", feature = "websocket", feature = "http"))]
+        #[serde(flatten)]
+        services: ServiceSettings,
+        app_name: String,
+    }
+
+    #[test]
+    fn test_flatten_into_app_settings() {
+        let settings: AppSettings = load_settings("tests/config/services.toml").unwrap();
+        assert_eq!(settings.app_name, "test-app");
+
+        #[cfg(feature = "jwt")]
+        assert_eq!(settings.services.jwt.access_token_duration.as_secs(), 10800);
+        #[cfg(feature = "websocket")]
+        {
+            assert_eq!(settings.services.websocket.host, "0.0.0.0");
+            assert_eq!(settings.services.websocket.port, 18123);
+        }
+        #[cfg(feature = "http")]
+        assert_eq!(settings.services.http.host, "127.0.0.1");
+    }
+
+    #[test]
+    fn test_sample_toml_deserializes() {
+        // SAFETY: the variables are only read by this test's sample file.
+        unsafe {
+            std::env::set_var("JWT_ACCESS_SECRET", "access_secret");
+            std::env::set_var("JWT_REFRESH_SECRET", "refresh_secret");
+            std::env::set_var("SURREALDB_PASSWORD", "root");
+        }
+        let path = std::env::temp_dir().join(format!("sur_sample_{}.toml", std::process::id()));
+        std::fs::write(&path, ServiceSettings::sample_toml()).unwrap();
+
+        let settings = ServiceSettings::load(path.to_str().unwrap()).unwrap();
+        assert!(settings.validate_all().is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_json_schema_lists_enabled_sections() {
+        let schema = ServiceSettings::json_schema();
+        let sections = schema["properties"].as_object().unwrap();
+        assert_eq!(sections.contains_key("jwt"), cfg!(feature = "jwt"));
+        assert_eq!(sections.contains_key("http"), cfg!(feature = "http"));
+        let required = schema["required"].as_array().unwrap();
+        assert_eq!(required.contains(&"jwt".into()), cfg!(feature = "jwt"));
+        assert!(!required.contains(&"password".into()));
+        assert!(!required.contains(&"otp".into()));
+        #[cfg(feature = "jwt")]
+        {
+            let jwt = schema["properties"]["jwt"]["required"].as_array().unwrap();
+            assert!(!jwt.contains(&"access_secret".into()));
+            assert!(!jwt.contains(&"refresh_secret".into()));
+            let keys = &schema["properties"]["jwt"]["properties"]["verify_keys"];
+            assert_eq!(keys["items"]["required"], serde_json::json!(["kid"]));
+            assert!(ServiceSettings::sample_toml().contains("\naccess_secret = "));
+        }
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_validate_sections() {
+        let settings = ServiceSettings::load("tests/config/services.toml").unwrap();
+        let Err(Error::ValidationError(errors)) = settings.validate_all() else {
+            panic!("Expected Error::ValidationError");
+        };
+        let paths: Vec<&str> = errors.errors().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["http.port"]);
+    }
+}
@@ -17,7 +17,7 @@ use tokio::{
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async, tungstenite::Message};
 
 use super::{JsonMessage, MsgReciver, MsgSender};
-use crate::error::{Error, Result};
+use crate::error::{Error, Result, ResultExt};
 
 type SocketReader = SplitStream<WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>>;
 type SocketWriter = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
@@ -48,7 +48,8 @@ impl WebSocketClient {
     }
 
     pub async fn send_json_message(&self, msg: JsonMessage) -> Result<()> {
-        let bin = serde_json::to_vec(&msg).map_err(|e| Error::ErrorMessage(e.to_string()))?;
+        let bin = serde_json::to_vec(&msg)
+            .with_context(|| format!("encoding ws message {}", msg.action))?;
         let event = ClientEvents::SendMessage(Message::binary(bin));
         self.send_message(event).await?;
         Ok(())
@@ -123,7 +124,7 @@ async fn receive_message(
             }
             Some(Ok(Message::Binary(bin))) => {
                 let parsed_msg: JsonMessage =
-                    serde_json::from_slice(&bin).map_err(|e| Error::ErrorMessage(e.to_string()))?;
+                    serde_json::from_slice(&bin).context("decoding ws frame from server")?;
                 tokio::spawn(process_message(parsed_msg, tx.clone(), router.clone()));
 
                 println!("收到二进制消息");
@@ -270,8 +271,8 @@ async fn process_message(
 ) -> Result<()> {
     match router.handle_message(&msg.action, msg.data).await {
         Some(message) => {
-            let bin =
-                serde_json::to_vec(&message).map_err(|e| Error::ErrorMessage(e.to_string()))?;
+            let bin = serde_json::to_vec(&message)
+                .with_context(|| format!("encoding reply to {}", msg.action))?;
             let event = ClientEvents::SendMessage(Message::binary(bin));
             tx.send(event)
                 .await
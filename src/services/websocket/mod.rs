@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::error::Error;
+
 pub mod client;
 pub mod handler;
 pub mod server;
@@ -13,3 +15,24 @@ pub struct JsonMessage {
     pub action: String,
     pub data: serde_json::Value,
 }
+
+impl JsonMessage {
+    /// Builds the error reply to `action` for `err`, carrying its code and user-safe message.
+    pub fn error(action: &str, err: &Error) -> Self {
+        let reply = ErrorReply {
+            code: err.code(),
+            message: err.user_message(),
+        };
+        Self {
+            action: action.to_string(),
+            data: serde_json::json!({ "error": reply }),
+        }
+    }
+}
+
+/// Error payload sent to websocket clients under the `error` key of `data`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ErrorReply {
+    pub code: u16,
+    pub message: String,
+}
@@ -8,6 +8,7 @@ pub mod server_router;
 use std::sync::Arc;
 
 use events::SocketEvents;
+use serde::Deserialize;
 use server_router::ServerRouter;
 use tokio::{
     net::TcpListener,
@@ -21,16 +22,113 @@ use tokio_tungstenite::{
     },
 };
 
-use crate::{error::Result, utils::string_util::QueryExtractor};
+use crate::{
+    error::Result,
+    services::Grants,
+    utils::{
+        Validate, ValidationErrors,
+        config_schema::{ConfigField, ConfigSchema, FieldKind},
+        string_util::QueryExtractor,
+    },
+};
 
 pub type SocketEventSender = UnboundedSender<SocketEvents>;
 
-pub async fn start<V>(port: u16, router: Arc<ServerRouter>, token_validator: V) -> Result<()>
+/// Identity of a connection, returned by the token validator.
+///
+/// A validator may return a bare connection id, which grants nothing; `0` rejects the handshake.
+#[derive(Debug, Clone, Default)]
+pub struct SocketAuth {
+    pub id: u32,
+    /// Checked against the scopes of routes added with [`ServerRouter::add_scoped_route`].
+    pub grants: Grants,
+}
+
+impl From<u32> for SocketAuth {
+    fn from(id: u32) -> Self {
+        Self {
+            id,
+            grants: Grants::default(),
+        }
+    }
+}
+
+impl From<(u32, Grants)> for SocketAuth {
+    fn from((id, grants): (u32, Grants)) -> Self {
+        Self { id, grants }
+    }
+}
+
+/// Struct representing the websocket server configuration parameters.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebsocketCfg {
+    /// Address to bind to, `0.0.0.0` by default.
+    #[serde(default = "default_host")]
+    pub host: String,
+    pub port: u16,
+}
+
+fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+impl ConfigSchema for WebsocketCfg {
+    fn description() -> &'static str {
+        "Websocket server."
+    }
+
+    fn fields() -> Vec<ConfigField> {
+        vec![
+            ConfigField::optional(
+                "host",
+                FieldKind::String,
+                "Address to bind to.",
+                "\"0.0.0.0\"",
+            ),
+            ConfigField::required("port", FieldKind::Port, "Port to listen on.", "18123"),
+        ]
+    }
+}
+
+impl Validate for WebsocketCfg {
+    fn validate(&self, errors: &mut ValidationErrors) {
+        errors.ensure(!self.host.is_empty(), "host", "must not be empty");
+        errors.ensure(self.port != 0, "port", "must not be 0");
+    }
+}
+
+pub async fn start<V, A>(port: u16, router: Arc<ServerRouter>, token_validator: V) -> Result<()>
+where
+    V: Fn(&str) -> A + Send + Sync + 'static,
+    A: Into<SocketAuth>,
+{
+    serve(&format!("0.0.0.0:{}", port), router, token_validator).await
+}
+
+/// Starts the websocket server on the host and port from `cfg`.
+pub async fn start_with_cfg<V, A>(
+    cfg: &WebsocketCfg,
+    router: Arc<ServerRouter>,
+    token_validator: V,
+) -> Result<()>
+where
+    V: Fn(&str) -> A + Send + Sync + 'static,
+    A: Into<SocketAuth>,
+{
+    serve(
+        &format!("{}:{}", cfg.host, cfg.port),
+        router,
+        token_validator,
+    )
+    .await
+}
+
+async fn serve<V, A>(addr: &str, router: Arc<ServerRouter>, token_validator: V) -> Result<()>
 where
-    V: Fn(&str) -> u32 + Send + Sync + 'static,
+    V: Fn(&str) -> A + Send + Sync + 'static,
+    A: Into<SocketAuth>,
 {
-    let addr = format!("0.0.0.0:{}", port);
-    let listener = TcpListener::bind(&addr).await?;
+    let listener = TcpListener::bind(addr).await?;
 
     println!("WebSocket Server is running on ws://{}", addr);
 
@@ -39,7 +137,7 @@ where
     tokio::spawn(manager::start_loop(receiver));
 
     while let Ok((stream, client_addr)) = listener.accept().await {
-        let mut id: u32 = 0;
+        let mut auth = SocketAuth::default();
 
         let callback = |req: &Request, mut res: Response| {
             if let Some(token) = req
@@ -47,8 +145,8 @@ where
                 .query()
                 .and_then(|query| query.extract_value("token").map(|t| t.to_string()))
             {
-                id = token_validator(&token);
-                if id == 0 {
+                auth = token_validator(&token).into();
+                if auth.id == 0 {
                     *res.status_mut() = http::StatusCode::UNAUTHORIZED;
                 }
             } else {
@@ -65,7 +163,7 @@ where
                     router.clone(),
                     ws_stream,
                     sender.clone(),
-                    id,
+                    auth,
                 ));
             }
         }
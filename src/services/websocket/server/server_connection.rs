@@ -13,10 +13,13 @@ use tokio::{
 };
 use tokio_tungstenite::{WebSocketStream, tungstenite::Message};
 
-use super::{SocketEventSender, server_router::ServerRouter};
+use super::{SocketAuth, SocketEventSender, server_router::ServerRouter};
 use crate::{
-    error::{Error, Result},
-    services::websocket::{JsonMessage, MsgReciver, MsgSender, server::events::SocketEvents},
+    error::{Error, Result, ResultExt},
+    services::{
+        Grants,
+        websocket::{JsonMessage, MsgReciver, MsgSender, server::events::SocketEvents},
+    },
 };
 
 /// Alias for the writing half of a WebSocket connection.
@@ -41,8 +44,9 @@ pub async fn handle_connection(
     router: Arc<ServerRouter>,
     ws_stream: WebSocketStream<TcpStream>,
     sender: SocketEventSender,
-    id: u32,
+    auth: SocketAuth,
 ) {
+    let id = auth.id;
     println!("socket id: {}", id);
 
     // Message channel
@@ -52,8 +56,6 @@ pub async fn handle_connection(
 
     let connection = Connection::new(id, msg_sender.clone());
 
-    let conn_id = connection.id;
-
     // Send a handshake event to the connection manager
     sender
         .send(SocketEvents::Handshake(tx, connection))
@@ -65,7 +67,7 @@ pub async fn handle_connection(
         router,
         msg_sender,
         sender,
-        conn_id,
+        auth,
         msg_reciever,
     )
     .await
@@ -78,7 +80,7 @@ async fn process_handshake(
     router: Arc<ServerRouter>,
     msg_sender: MsgSender,
     socket_event_sender: SocketEventSender,
-    connection_id: u32,
+    auth: SocketAuth,
     msg_reciever: MsgReciver,
 ) -> Result<()> {
     match rx.await {
@@ -91,7 +93,8 @@ async fn process_handshake(
                     socket_reader,
                     msg_sender,
                     socket_event_sender,
-                    connection_id,
+                    auth.id,
+                    Arc::new(auth.grants),
                 )
                 .await
             }
@@ -118,6 +121,7 @@ async fn handle_msg(
     tx: MsgSender,
     socket_event_sender: SocketEventSender,
     connection_id: u32,
+    grants: Arc<Grants>,
 ) -> Result<()> {
     while let Some(message) = read.next().await {
         match message {
@@ -125,13 +129,14 @@ async fn handle_msg(
                 println!("Received text message: {}", msg);
             }
             Ok(Message::Binary(bin)) => {
-                let parsed_msg: JsonMessage =
-                    serde_json::from_slice(&bin).map_err(|e| Error::ErrorMessage(e.to_string()))?;
+                let parsed_msg: JsonMessage = serde_json::from_slice(&bin)
+                    .with_context(|| format!("decoding ws frame from conn {}", connection_id))?;
                 tokio::spawn(process_message(
                     parsed_msg,
                     router.clone(),
                     tx.clone(),
                     socket_event_sender.clone(),
+                    grants.clone(),
                 ));
             }
             Ok(Message::Ping(_ping)) => {}
@@ -168,14 +173,15 @@ async fn process_message(
     router: Arc<ServerRouter>,
     tx: MsgSender,
     socket_event_sender: SocketEventSender,
+    grants: Arc<Grants>,
 ) -> Result<()> {
     match router
-        .handle_message(&message.action, message.data, socket_event_sender)
+        .handle_message_with_grants(&message.action, message.data, socket_event_sender, &grants)
         .await
     {
         Some(response) => {
-            let bin =
-                serde_json::to_vec(&response).map_err(|e| Error::ErrorMessage(e.to_string()))?;
+            let bin = serde_json::to_vec(&response)
+                .with_context(|| format!("encoding reply to {}", message.action))?;
             let message = Message::binary(bin);
             tx.send(message)
                 .await
@@ -1,7 +1,7 @@
 use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
 
 use super::SocketEventSender;
-use crate::services::websocket::JsonMessage;
+use crate::services::{Grants, websocket::JsonMessage};
 
 pub trait Handler {
     fn call(
@@ -25,8 +25,13 @@ where
     }
 }
 
+struct Route {
+    handler: Arc<dyn Handler + Send + Sync>,
+    scopes: &'static [&'static str],
+}
+
 pub struct ServerRouter {
-    routes: HashMap<&'static str, Arc<dyn Handler + Send + Sync>>,
+    routes: HashMap<&'static str, Route>,
 }
 
 impl ServerRouter {
@@ -40,21 +45,100 @@ impl ServerRouter {
     where
         H: Handler + Send + Sync + 'static,
     {
-        self.routes.insert(action, Arc::new(handler));
+        self.add_scoped_route(action, &[], handler)
+    }
+
+    /// Adds a route only connections granted every scope of `scopes` may call.
+    ///
+    /// Other connections get an error reply with the `PermissionDenied` code.
+    pub fn add_scoped_route<H>(
+        &mut self,
+        action: &'static str,
+        scopes: &'static [&'static str],
+        handler: H,
+    ) -> &mut Self
+    where
+        H: Handler + Send + Sync + 'static,
+    {
+        let route = Route {
+            handler: Arc::new(handler),
+            scopes,
+        };
+        self.routes.insert(action, route);
         self
     }
 
+    /// Dispatches a message from a connection without grants, so scoped routes are denied.
     pub async fn handle_message(
         &self,
         action: &str,
         data: serde_json::Value,
         tx: SocketEventSender,
     ) -> Option<JsonMessage> {
-        if let Some(handler) = self.routes.get(action) {
-            handler.call(data, tx).await
+        self.handle_message_with_grants(action, data, tx, &Grants::default())
+            .await
+    }
+
+    /// Dispatches a message from a connection granted `grants`.
+    pub async fn handle_message_with_grants(
+        &self,
+        action: &str,
+        data: serde_json::Value,
+        tx: SocketEventSender,
+        grants: &Grants,
+    ) -> Option<JsonMessage> {
+        if let Some(route) = self.routes.get(action) {
+            if let Err(e) = grants.require_scopes(route.scopes) {
+                return Some(JsonMessage::error(action, &e));
+            }
+            route.handler.call(data, tx).await
         } else {
             eprintln!("Unknown action: {}", action);
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{error::Error, services::websocket::ErrorReply};
+
+    async fn echo(data: serde_json::Value, _tx: SocketEventSender) -> Option<JsonMessage> {
+        Some(JsonMessage {
+            action: "echo".to_string(),
+            data,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_scoped_route() {
+        let mut router = ServerRouter::new();
+        router
+            .add_route("echo", echo)
+            .add_scoped_route("admin_echo", &["admin"], echo);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let admin = Grants::new().with_scopes(["admin"]);
+
+        let reply = router
+            .handle_message("echo", serde_json::json!(1), tx.clone())
+            .await
+            .unwrap();
+        assert_eq!(reply.data, 1);
+        let reply = router
+            .handle_message_with_grants("admin_echo", serde_json::json!(2), tx.clone(), &admin)
+            .await
+            .unwrap();
+        assert_eq!(reply.data, 2);
+
+        let reply = router
+            .handle_message("admin_echo", serde_json::json!(3), tx)
+            .await
+            .unwrap();
+        assert_eq!(reply.action, "admin_echo");
+        let error: ErrorReply = serde_json::from_value(reply.data["error"].clone()).unwrap();
+        assert_eq!(error.code, Error::PermissionDenied(String::new()).code());
+    }
+}
@@ -0,0 +1,294 @@
+use std::{collections::HashSet, fmt};
+
+use config::{Value, ValueKind};
+use serde::Serialize;
+
+use super::secret::{ENV_REF_PREFIX, FILE_REF_PREFIX};
+
+/// Origin reported by the `config` crate for values read from environment variables.
+const ENV_ORIGIN: &str = "the environment";
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Start of a `${NAME}` placeholder, which may hide a templated secret.
+const PLACEHOLDER_START: &str = "${";
+
+/// Key suffixes whose values are always masked.
+const SECRET_SUFFIXES: [&str; 4] = ["secret", "password", "token", "key"];
+
+/// Where the winning value of a key came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "source", rename_all = "snake_case")]
+pub enum ValueOrigin {
+    /// A default registered on the loader, or an in-memory string source.
+    Default,
+    /// A config file, by path.
+    File(String),
+    /// An environment variable, by name.
+    Env(String),
+}
+
+impl fmt::Display for ValueOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueOrigin::Default => f.write_str("default"),
+            ValueOrigin::File(path) => write!(f, "file {}", path),
+            ValueOrigin::Env(name) => write!(f, "env {}", name),
+        }
+    }
+}
+
+/// A single leaf of the merged configuration.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigEntry {
+    /// Dotted key path, e.g. `jwt.audience` or `cors.origins[0]`.
+    pub key: String,
+    /// The value, or `"[REDACTED]"` when `masked` is set.
+    pub value: serde_json::Value,
+    pub origin: ValueOrigin,
+    pub masked: bool,
+}
+
+/// The merged configuration with the origin of every value, safe to log or expose.
+///
+/// Values are masked when their key ends with `secret`, `password`, `token` or `key`, when
+/// they were given as an `env://` or `file://` reference or contain a `${...}` placeholder,
+/// or when their key was marked with [`crate::utils::SettingsLoader::secret_key`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EffectiveConfig {
+    entries: Vec<ConfigEntry>,
+}
+
+impl EffectiveConfig {
+    /// Returns the entries sorted by key.
+    pub fn entries(&self) -> &[ConfigEntry] {
+        &self.entries
+    }
+
+    /// Returns the entry of a dotted key, if present.
+    pub fn get(&self, key: &str) -> Option<&ConfigEntry> {
+        self.entries.iter().find(|entry| entry.key == key)
+    }
+}
+
+impl fmt::Display for EffectiveConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{} = {} ({})", entry.key, entry.value, entry.origin)?;
+        }
+        Ok(())
+    }
+}
+
+/// Options needed to turn origins and keys into an [`EffectiveConfig`].
+pub(crate) struct DumpOptions<'a> {
+    pub env_prefix: Option<&'a str>,
+    pub env_separator: &'a str,
+    pub secret_keys: &'a [String],
+}
+
+/// Flattens the merged tree into entries.
+///
+/// `raw` is the tree before secret references were resolved and `resolved` the tree after;
+/// both have the same shape.
+pub(crate) fn collect(raw: &Value, resolved: &Value, options: &DumpOptions<'_>) -> EffectiveConfig {
+    let mut references = HashSet::new();
+    for (key, value) in flatten(raw) {
+        if let ValueKind::String(s) = &value.kind
+            && (s.starts_with(ENV_REF_PREFIX)
+                || s.starts_with(FILE_REF_PREFIX)
+                || s.contains(PLACEHOLDER_START))
+        {
+            references.insert(key);
+        }
+    }
+
+    let mut entries: Vec<ConfigEntry> = flatten(resolved)
+        .into_iter()
+        .map(|(key, value)| {
+            let masked = references.contains(&key) || is_secret_key(&key, options.secret_keys);
+            let origin = origin_of(&key, value, options);
+            let value = if masked {
+                serde_json::Value::from(REDACTED)
+            } else {
+                to_json(&value.kind)
+            };
+            ConfigEntry {
+                key,
+                value,
+                origin,
+                masked,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    EffectiveConfig { entries }
+}
+
+fn flatten(value: &Value) -> Vec<(String, &Value)> {
+    let mut out = Vec::new();
+    flatten_into(value, String::new(), &mut out);
+    out
+}
+
+fn flatten_into<'a>(value: &'a Value, path: String, out: &mut Vec<(String, &'a Value)>) {
+    match &value.kind {
+        ValueKind::Table(table) if !table.is_empty() => {
+            for (key, child) in table {
+                let child_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                flatten_into(child, child_path, out);
+            }
+        }
+        ValueKind::Array(items) if !items.is_empty() => {
+            for (i, child) in items.iter().enumerate() {
+                flatten_into(child, format!("{}[{}]", path, i), out);
+            }
+        }
+        _ => out.push((path, value)),
+    }
+}
+
+fn is_secret_key(key: &str, secret_keys: &[String]) -> bool {
+    if secret_keys
+        .iter()
+        .any(|secret| key == secret || key.starts_with(&format!("{}.", secret)))
+    {
+        return true;
+    }
+    let last = key.rsplit('.').next().unwrap_or(key);
+    let last = last.split('[').next().unwrap_or(last).to_ascii_lowercase();
+    SECRET_SUFFIXES.iter().any(|suffix| last.ends_with(suffix))
+}
+
+fn origin_of(key: &str, value: &Value, options: &DumpOptions<'_>) -> ValueOrigin {
+    match value.origin() {
+        None => ValueOrigin::Default,
+        Some(ENV_ORIGIN) => ValueOrigin::Env(env_var_name(key, options)),
+        Some(path) => ValueOrigin::File(path.to_string()),
+    }
+}
+
+/// Returns the name of the environment variable that set `key`.
+fn env_var_name(key: &str, options: &DumpOptions<'_>) -> String {
+    let separator = options.env_separator;
+    let mut name = key.replace('.', separator);
+    if let Some(prefix) = options.env_prefix {
+        name = format!("{}{}{}", prefix, separator, name);
+    }
+    std::env::vars()
+        .map(|(var, _)| var)
+        .find(|var| var.eq_ignore_ascii_case(&name))
+        .unwrap_or_else(|| name.to_ascii_uppercase())
+}
+
+fn to_json(kind: &ValueKind) -> serde_json::Value {
+    match kind {
+        ValueKind::Nil => serde_json::Value::Null,
+        ValueKind::Boolean(b) => (*b).into(),
+        ValueKind::I64(n) => (*n).into(),
+        ValueKind::U64(n) => (*n).into(),
+        ValueKind::I128(n) => i64::try_from(*n).map_or_else(|_| n.to_string().into(), Into::into),
+        ValueKind::U128(n) => u64::try_from(*n).map_or_else(|_| n.to_string().into(), Into::into),
+        ValueKind::Float(n) => (*n).into(),
+        ValueKind::String(s) => s.as_str().into(),
+        ValueKind::Table(_) => serde_json::Value::Object(Default::default()),
+        ValueKind::Array(_) => serde_json::Value::Array(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::SettingsLoader;
+
+    #[test]
+    fn test_reports_origin_of_each_value() {
+        // SAFETY: the variable uses a prefix unique to this test.
+        unsafe { std::env::set_var("SUR_DUMP_TEST__SERVER__PORT", "7000") };
+
+        let dump = SettingsLoader::new("tests/config/default.toml")
+            .default("server.workers", 4)
+            .profile("dev")
+            .env_prefix("SUR_DUMP_TEST")
+            .effective_config()
+            .unwrap();
+
+        let name = dump.get("name").unwrap();
+        assert_eq!(name.value, "base");
+        assert!(matches!(&name.origin, ValueOrigin::File(path) if path.ends_with("default.toml")));
+        let host = dump.get("server.host").unwrap();
+        assert!(matches!(&host.origin, ValueOrigin::File(path) if path.ends_with("dev.toml")));
+        let port = dump.get("server.port").unwrap();
+        assert_eq!(port.value, "7000");
+        assert_eq!(
+            port.origin,
+            ValueOrigin::Env("SUR_DUMP_TEST__SERVER__PORT".to_string())
+        );
+        let workers = dump.get("server.workers").unwrap();
+        assert_eq!(workers.value, 4);
+        assert_eq!(workers.origin, ValueOrigin::Default);
+
+        let keys: Vec<&str> = dump.entries().iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            ["name", "server.host", "server.port", "server.workers"]
+        );
+    }
+
+    #[test]
+    fn test_masks_secrets() {
+        // SAFETY: the variable name is unique to this test.
+        unsafe { std::env::set_var("SUR_DUMP_TEST_DSN", "postgres://user:pw@db") };
+        let path = std::env::temp_dir().join(format!("sur_dump_{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+            [jwt]
+            access_secret = "hunter2"
+            access_token_duration = "3h"
+            [db]
+            password = "hunter2"
+            dsn = "env://SUR_DUMP_TEST_DSN"
+            url = "ws://user:${DB_PASS}@db"
+            [billing]
+            account = "acme"
+            "#,
+        )
+        .unwrap();
+
+        let dump = SettingsLoader::new(path.to_str().unwrap())
+            .secret_key("billing")
+            .effective_config()
+            .unwrap();
+
+        for key in [
+            "jwt.access_secret",
+            "db.password",
+            "db.dsn",
+            "db.url",
+            "billing.account",
+        ] {
+            let entry = dump.get(key).unwrap();
+            assert!(entry.masked, "{} should be masked", key);
+            assert_eq!(entry.value, REDACTED);
+        }
+        assert!(!dump.get("jwt.access_token_duration").unwrap().masked);
+
+        let text = dump.to_string();
+        assert!(!text.contains("hunter2"));
+        assert!(!text.contains("user:pw"));
+        assert!(!text.contains("DB_PASS"));
+        assert!(text.contains("jwt.access_token_duration = \"3h\" (file "));
+        let json = serde_json::to_string(&dump).unwrap();
+        assert!(!json.contains("hunter2"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
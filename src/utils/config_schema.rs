@@ -0,0 +1,308 @@
+use std::fmt::Write;
+
+use serde_json::{Map, Value, json};
+
+/// Pattern accepted by [`crate::utils::ConfigDuration`] strings.
+const DURATION_PATTERN: &str = r"^\s*(\d+\s*(ms|s|m|h|d|w)\s*)+$";
+
+/// Pattern accepted by [`crate::utils::ByteSize`] strings.
+const BYTE_SIZE_PATTERN: &str = r"^\s*\d+\s*([kKmMgGtT][iI]?)?[bB]\s*$";
+
+/// Type of a configuration value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    String,
+    /// A string that may be given as an `env://` or `file://` reference.
+    Secret,
+    Integer,
+    /// A TCP port between 1 and 65535.
+    Port,
+    Boolean,
+    /// Seconds or a [`crate::utils::ConfigDuration`] string such as `"15m"`.
+    Duration,
+    /// Bytes or a [`crate::utils::ByteSize`] string such as `"4MiB"`.
+    ByteSize,
+    StringList,
+    /// An array of tables with the given keys, such as `[[jwt.verify_keys]]`.
+    TableList(&'static [ConfigField]),
+}
+
+impl FieldKind {
+    fn json_schema(&self) -> Value {
+        match self {
+            FieldKind::String => json!({ "type": "string" }),
+            FieldKind::Secret => json!({ "type": "string", "writeOnly": true }),
+            FieldKind::Integer => json!({ "type": "integer" }),
+            FieldKind::Port => json!({ "type": "integer", "minimum": 1, "maximum": 65535 }),
+            FieldKind::Boolean => json!({ "type": "boolean" }),
+            FieldKind::Duration => json!({
+                "oneOf": [
+                    { "type": "integer", "minimum": 0 },
+                    { "type": "string", "pattern": DURATION_PATTERN }
+                ]
+            }),
+            FieldKind::ByteSize => json!({
+                "oneOf": [
+                    { "type": "integer", "minimum": 0 },
+                    { "type": "string", "pattern": BYTE_SIZE_PATTERN }
+                ]
+            }),
+            FieldKind::StringList => json!({ "type": "array", "items": { "type": "string" } }),
+            FieldKind::TableList(fields) => {
+                json!({ "type": "array", "items": object_schema(fields) })
+            }
+        }
+    }
+}
+
+/// Description of a single configuration key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigField {
+    pub key: &'static str,
+    pub kind: FieldKind,
+    pub description: &'static str,
+    /// Value written to the sample file, as a TOML literal (e.g. `"\"15m\""` or `"8080"`).
+    pub sample: &'static str,
+    pub required: bool,
+    /// Whether the key is commented out in the sample file, by default only if it is optional.
+    pub commented: bool,
+}
+
+impl ConfigField {
+    /// Describes a key that must be present.
+    pub const fn required(
+        key: &'static str,
+        kind: FieldKind,
+        description: &'static str,
+        sample: &'static str,
+    ) -> Self {
+        Self {
+            key,
+            kind,
+            description,
+            sample,
+            required: true,
+            commented: false,
+        }
+    }
+
+    /// Describes a key that may be omitted; it is commented out in the sample file.
+    pub const fn optional(
+        key: &'static str,
+        kind: FieldKind,
+        description: &'static str,
+        sample: &'static str,
+    ) -> Self {
+        Self {
+            key,
+            kind,
+            description,
+            sample,
+            required: false,
+            commented: true,
+        }
+    }
+
+    /// Writes an optional key uncommented in the sample file, for keys the default
+    /// configuration needs but others do not.
+    pub const fn uncommented(mut self) -> Self {
+        self.commented = false;
+        self
+    }
+}
+
+/// Implemented by configuration structs to describe their keys.
+///
+/// # Example
+///
+/// ```
+/// use service_utils_rs::utils::config_schema::{
+///     ConfigField, ConfigSchema, ConfigSection, FieldKind, sample_toml,
+/// };
+///
+/// struct CacheCfg;
+///
+/// impl ConfigSchema for CacheCfg {
+///     fn description() -> &'static str {
+///         "In-memory cache."
+///     }
+///
+///     fn fields() -> Vec<ConfigField> {
+///         vec![ConfigField::required(
+///             "capacity",
+///             FieldKind::Integer,
+///             "Maximum number of entries.",
+///             "1024",
+///         )]
+///     }
+/// }
+///
+/// let sample = sample_toml(&[ConfigSection::of::<CacheCfg>("cache")]);
+/// assert!(sample.contains("[cache]\n"));
+/// ```
+pub trait ConfigSchema {
+    /// One-line description written above the section.
+    fn description() -> &'static str;
+
+    /// Keys of the section, in the order they are written to the sample file.
+    fn fields() -> Vec<ConfigField>;
+}
+
+/// A configuration struct placed under a table name.
+#[derive(Debug, Clone)]
+pub struct ConfigSection {
+    pub name: String,
+    pub description: &'static str,
+    pub fields: Vec<ConfigField>,
+    /// Whether the table must be present, i.e. the struct has no `#[serde(default)]`.
+    pub required: bool,
+}
+
+impl ConfigSection {
+    /// Describes `T` read from the table `name`.
+    pub fn of<T>(name: &str) -> Self
+    where
+        T: ConfigSchema,
+    {
+        Self {
+            name: name.to_string(),
+            description: T::description(),
+            fields: T::fields(),
+            required: true,
+        }
+    }
+
+    /// Marks the table as optional, for sections read with `#[serde(default)]`.
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+}
+
+/// Renders a commented sample TOML file for the given sections.
+pub fn sample_toml(sections: &[ConfigSection]) -> String {
+    let mut out = String::new();
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "# {}", section.description);
+        let _ = writeln!(out, "[{}]", section.name);
+        for field in &section.fields {
+            let _ = writeln!(out, "# {}", field.description);
+            let prefix = if field.commented { "# " } else { "" };
+            let _ = writeln!(out, "{}{} = {}", prefix, field.key, field.sample);
+        }
+    }
+    out
+}
+
+/// Builds a JSON Schema (draft 2020-12) describing the given sections.
+pub fn json_schema(sections: &[ConfigSection]) -> Value {
+    let mut properties = Map::new();
+    for section in sections {
+        let mut schema = object_schema(&section.fields);
+        schema["description"] = json!(section.description);
+        properties.insert(section.name.clone(), schema);
+    }
+    let required: Vec<&str> = sections
+        .iter()
+        .filter(|s| s.required)
+        .map(|s| s.name.as_str())
+        .collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn object_schema(fields: &[ConfigField]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for field in fields {
+        let mut schema = field.kind.json_schema();
+        schema["description"] = json!(field.description);
+        properties.insert(field.key.to_string(), schema);
+        if field.required {
+            required.push(field.key);
+        }
+    }
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::utils::ConfigDuration;
+
+    #[derive(Debug, Deserialize)]
+    struct ServerCfg {
+        host: Option<String>,
+        port: u16,
+        timeout: ConfigDuration,
+    }
+
+    impl ConfigSchema for ServerCfg {
+        fn description() -> &'static str {
+            "Server settings."
+        }
+
+        fn fields() -> Vec<ConfigField> {
+            vec![
+                ConfigField::optional("host", FieldKind::String, "Bind address.", "\"0.0.0.0\""),
+                ConfigField::required("port", FieldKind::Port, "Listen port.", "8080"),
+                ConfigField::required("timeout", FieldKind::Duration, "Idle timeout.", "\"30s\""),
+            ]
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Settings {
+        server: ServerCfg,
+    }
+
+    #[test]
+    fn test_sample_toml_round_trips() {
+        let sample = sample_toml(&[ConfigSection::of::<ServerCfg>("server")]);
+        assert_eq!(
+            sample,
+            "# Server settings.\n[server]\n# Bind address.\n# host = \"0.0.0.0\"\n# Listen \
+             port.\nport = 8080\n# Idle timeout.\ntimeout = \"30s\"\n"
+        );
+
+        let settings: Settings = config::Config::builder()
+            .add_source(config::File::from_str(&sample, config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(settings.server.host, None);
+        assert_eq!(settings.server.port, 8080);
+        assert_eq!(settings.server.timeout.as_secs(), 30);
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = json_schema(&[
+            ConfigSection::of::<ServerCfg>("server"),
+            ConfigSection::of::<ServerCfg>("admin").optional(),
+        ]);
+        assert_eq!(schema["required"], json!(["server"]));
+        assert!(schema["properties"]["admin"].is_object());
+        let server = &schema["properties"]["server"];
+        assert_eq!(server["required"], json!(["port", "timeout"]));
+        assert_eq!(server["properties"]["port"]["maximum"], json!(65535));
+        assert_eq!(
+            server["properties"]["host"]["description"],
+            json!("Bind address.")
+        );
+    }
+}
@@ -1,25 +1,355 @@
-use config::{Config, File};
+use std::path::Path;
+
+use config::{Config, Environment, File, FileFormat, Value};
 use serde::de::DeserializeOwned;
 
+use super::{
+    config_dump::{self, DumpOptions, EffectiveConfig},
+    secret::resolve_secret_refs,
+    validate::Validate,
+};
 use crate::error::Result;
 
+/// Environment variable consulted for the active profile when none is set explicitly.
+pub const DEFAULT_PROFILE_ENV: &str = "APP_ENV";
+
+/// Default separator between the prefix and nested keys of environment variables.
+pub const DEFAULT_ENV_SEPARATOR: &str = "__";
+
+/// Loads settings from a single file and resolves its secret references.
+///
+/// Use [`SettingsLoader`] for profiles, local overrides and environment variables.
 pub fn load_settings<T>(config_path: &str) -> Result<T>
 where
     T: DeserializeOwned,
 {
-    let config = Config::builder()
+    let mut value = Config::builder()
         .add_source(File::with_name(config_path))
-        .build()?;
-
-    let r = config.try_deserialize()?;
+        .build()?
+        .cache;
+    resolve_secret_refs(&mut value)?;
+    let r = value.try_deserialize()?;
     Ok(r)
 }
 
+/// Loads settings from an in-memory string in the given format.
+///
+/// Useful for tests and for defaults embedded with `include_str!`.
+pub fn load_settings_from_str<T>(content: &str, format: ConfigFormat) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    SettingsLoader::from_string(content, format).load()
+}
+
+/// Format of a config source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+    Json5,
+}
+
+impl ConfigFormat {
+    /// Returns the format matching a file extension such as `yml`, if known.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            "json" => Some(ConfigFormat::Json),
+            "json5" => Some(ConfigFormat::Json5),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigFormat> for FileFormat {
+    fn from(format: ConfigFormat) -> Self {
+        match format {
+            ConfigFormat::Toml => FileFormat::Toml,
+            ConfigFormat::Yaml => FileFormat::Yaml,
+            ConfigFormat::Json => FileFormat::Json,
+            ConfigFormat::Json5 => FileFormat::Json5,
+        }
+    }
+}
+
+/// The required first layer of a [`SettingsLoader`].
+#[derive(Debug, Clone)]
+enum BaseSource {
+    File(String),
+    Str {
+        content: String,
+        format: ConfigFormat,
+    },
+}
+
+/// Loads settings like [`load_settings`] and runs their [`Validate`] checks.
+///
+/// Every semantic problem is reported at once in an `Error::ValidationError`.
+pub fn load_and_validate<T>(config_path: &str) -> Result<T>
+where
+    T: DeserializeOwned + Validate,
+{
+    let settings: T = load_settings(config_path)?;
+    settings.validate_all()?;
+    Ok(settings)
+}
+
+/// Builder for settings assembled from several layered sources.
+///
+/// Sources are merged in the following order, later ones overriding earlier ones:
+///
+/// 1. defaults registered with [`SettingsLoader::default`] and [`SettingsLoader::defaults_str`]
+/// 2. the base file, or the base string of [`SettingsLoader::from_string`]
+/// 3. the profile file (e.g. `config/dev.toml` next to `config/default.toml`)
+/// 4. the local override file
+/// 5. environment variables (e.g. `APP__JWT__ACCESS_SECRET` for `jwt.access_secret`)
+///
+/// File formats are guessed from the extension unless set with [`SettingsLoader::format`].
+///
+/// # Example
+///
+/// ```no_run
+/// use serde::Deserialize;
+/// use service_utils_rs::utils::SettingsLoader;
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Settings {
+///     name: String,
+/// }
+///
+/// let settings: Settings = SettingsLoader::new("config/default.toml")
+///     .profile_env("APP_ENV")
+///     .local_override("config/local.toml")
+///     .env_prefix("APP")
+///     .load()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SettingsLoader {
+    base: BaseSource,
+    format: Option<ConfigFormat>,
+    profile: Option<String>,
+    profile_env: String,
+    local_override: Option<String>,
+    env_prefix: Option<String>,
+    env_separator: String,
+    defaults: Vec<(String, Value)>,
+    embedded_defaults: Vec<(String, ConfigFormat)>,
+    secret_keys: Vec<String>,
+}
+
+impl SettingsLoader {
+    /// Creates a loader for the given base file. The base file is required.
+    pub fn new(base_path: &str) -> Self {
+        Self::with_base(BaseSource::File(base_path.to_string()))
+    }
+
+    /// Creates a loader whose base layer is an in-memory string.
+    ///
+    /// Such a loader has no profile file, since there is no directory to look for it in.
+    pub fn from_string(content: &str, format: ConfigFormat) -> Self {
+        Self::with_base(BaseSource::Str {
+            content: content.to_string(),
+            format,
+        })
+    }
+
+    fn with_base(base: BaseSource) -> Self {
+        Self {
+            base,
+            format: None,
+            profile: None,
+            profile_env: DEFAULT_PROFILE_ENV.to_string(),
+            local_override: None,
+            env_prefix: None,
+            env_separator: DEFAULT_ENV_SEPARATOR.to_string(),
+            defaults: Vec::new(),
+            embedded_defaults: Vec::new(),
+            secret_keys: Vec::new(),
+        }
+    }
+
+    /// Registers a default value for a dotted key such as `http.port`.
+    pub fn default<V>(mut self, key: &str, value: V) -> Self
+    where
+        V: Into<Value>,
+    {
+        self.defaults.push((key.to_string(), value.into()));
+        self
+    }
+
+    /// Registers defaults from an in-memory string, e.g. a file embedded with `include_str!`.
+    ///
+    /// They are merged after the defaults of [`SettingsLoader::default`], in the order added.
+    pub fn defaults_str(mut self, content: &str, format: ConfigFormat) -> Self {
+        self.embedded_defaults.push((content.to_string(), format));
+        self
+    }
+
+    /// Sets the format of every file source instead of guessing it from the extension.
+    pub fn format(mut self, format: ConfigFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Sets the active profile explicitly, taking precedence over the profile variable.
+    pub fn profile(mut self, profile: &str) -> Self {
+        self.profile = Some(profile.to_string());
+        self
+    }
+
+    /// Sets the environment variable the active profile is read from (`APP_ENV` by default).
+    pub fn profile_env(mut self, var: &str) -> Self {
+        self.profile_env = var.to_string();
+        self
+    }
+
+    /// Adds an optional local override file, merged on top of the profile file.
+    pub fn local_override(mut self, path: &str) -> Self {
+        self.local_override = Some(path.to_string());
+        self
+    }
+
+    /// Enables environment overrides for variables starting with `prefix`.
+    pub fn env_prefix(mut self, prefix: &str) -> Self {
+        self.env_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Sets the separator used between the prefix and nested keys (`__` by default).
+    pub fn env_separator(mut self, separator: &str) -> Self {
+        self.env_separator = separator.to_string();
+        self
+    }
+
+    /// Marks a dotted key, or a whole section, as secret in [`SettingsLoader::effective_config`].
+    pub fn secret_key(mut self, key: &str) -> Self {
+        self.secret_keys.push(key.to_string());
+        self
+    }
+
+    /// Returns the active profile, if any.
+    pub fn active_profile(&self) -> Option<String> {
+        self.profile
+            .clone()
+            .or_else(|| std::env::var(&self.profile_env).ok())
+            .filter(|profile| !profile.is_empty())
+    }
+
+    /// Returns the path of the profile file for the active profile, if any.
+    ///
+    /// The profile file lives next to the base file and keeps its extension, so
+    /// `config/default.toml` with profile `dev` resolves to `config/dev.toml`.
+    pub fn profile_path(&self) -> Option<String> {
+        let BaseSource::File(base_path) = &self.base else {
+            return None;
+        };
+        let profile = self.active_profile()?;
+        let base = Path::new(base_path);
+        let mut path = base.with_file_name(&profile);
+        if let Some(ext) = base.extension() {
+            path.set_extension(ext);
+        }
+        Some(path.to_string_lossy().into_owned())
+    }
+
+    /// Returns the file names of every file source, in merge order.
+    ///
+    /// Names may omit the extension, in which case the `config` crate guesses it on load.
+    pub fn file_sources(&self) -> Vec<String> {
+        let mut files = Vec::new();
+        if let BaseSource::File(path) = &self.base {
+            files.push(path.clone());
+        }
+        files.extend(self.profile_path());
+        files.extend(self.local_override.clone());
+        files
+    }
+
+    /// Merges all sources into a `Config`.
+    pub fn build(&self) -> Result<Config> {
+        let mut builder = Config::builder();
+        for (key, value) in &self.defaults {
+            builder = builder.set_default(key.as_str(), value.clone())?;
+        }
+        for (content, format) in &self.embedded_defaults {
+            builder = builder.add_source(File::from_str(content, FileFormat::from(*format)));
+        }
+        builder = match &self.base {
+            BaseSource::File(path) => builder.add_source(self.file(path)),
+            BaseSource::Str { content, format } => {
+                builder.add_source(File::from_str(content, FileFormat::from(*format)))
+            }
+        };
+        if let Some(path) = self.profile_path() {
+            builder = builder.add_source(self.file(&path).required(false));
+        }
+        if let Some(path) = &self.local_override {
+            builder = builder.add_source(self.file(path).required(false));
+        }
+        if let Some(prefix) = &self.env_prefix {
+            builder =
+                builder.add_source(Environment::with_prefix(prefix).separator(&self.env_separator));
+        }
+        Ok(builder.build()?)
+    }
+
+    fn file(&self, path: &str) -> File<config::FileSourceFile, FileFormat> {
+        match self.format {
+            Some(format) => File::with_name(path).format(format.into()),
+            None => File::with_name(path),
+        }
+    }
+
+    /// Merges all sources, resolves secret references and deserializes the result into `T`.
+    ///
+    /// See [`resolve_secret_refs`] for the supported reference forms.
+    pub fn load<T>(&self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let mut value = self.build()?.cache;
+        resolve_secret_refs(&mut value)?;
+        let r = value.try_deserialize()?;
+        Ok(r)
+    }
+
+    /// Merges all sources and returns every value together with the source it came from.
+    ///
+    /// Secret values are masked, so the result can be logged at startup or served on an admin
+    /// endpoint. See [`EffectiveConfig`] for the masking rules.
+    pub fn effective_config(&self) -> Result<EffectiveConfig> {
+        let raw = self.build()?.cache;
+        let mut resolved = raw.clone();
+        resolve_secret_refs(&mut resolved)?;
+        let options = DumpOptions {
+            env_prefix: self.env_prefix.as_deref(),
+            env_separator: &self.env_separator,
+            secret_keys: &self.secret_keys,
+        };
+        Ok(config_dump::collect(&raw, &resolved, &options))
+    }
+
+    /// Loads the settings like [`SettingsLoader::load`] and runs their [`Validate`] checks.
+    pub fn load_and_validate<T>(&self) -> Result<T>
+    where
+        T: DeserializeOwned + Validate,
+    {
+        let settings: T = self.load()?;
+        settings.validate_all()?;
+        Ok(settings)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use serde::Deserialize;
 
     use super::*;
+    use crate::utils::{ConfigDuration, ValidationErrors};
 
     #[derive(Debug, Deserialize)]
     pub struct Settings {
@@ -31,6 +361,48 @@ mod tests {
         pub test_key: String,
     }
 
+    #[derive(Debug, Deserialize)]
+    pub struct LayeredSettings {
+        pub name: String,
+        pub server: ServerConfig,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct ServerConfig {
+        pub host: String,
+        pub port: u16,
+        pub workers: u16,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    pub struct FormatSettings {
+        pub name: String,
+        pub debug: bool,
+        pub ratio: f64,
+        pub tags: Vec<String>,
+        pub timeout: ConfigDuration,
+        pub server: FormatServer,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    pub struct FormatServer {
+        pub host: String,
+        pub port: u16,
+    }
+
+    impl Validate for LayeredSettings {
+        fn validate(&self, errors: &mut ValidationErrors) {
+            errors.ensure(self.name != "base", "name", "must be overridden");
+            errors.nested("server", &self.server);
+        }
+    }
+
+    impl Validate for ServerConfig {
+        fn validate(&self, errors: &mut ValidationErrors) {
+            errors.ensure(self.workers > 0, "workers", "must be greater than 0");
+        }
+    }
+
     #[test]
     fn test_load_settings() {
         let config_path = "tests/test_config.toml"; // Adjust the path as needed
@@ -39,4 +411,147 @@ mod tests {
         let config = result.unwrap();
         assert_eq!(config.test.test_key, "test_value");
     }
+
+    #[test]
+    fn test_profile_path() {
+        let loader = SettingsLoader::new("tests/config/default.toml").profile("dev");
+        assert_eq!(
+            loader.profile_path().as_deref(),
+            Some("tests/config/dev.toml")
+        );
+
+        let loader = SettingsLoader::new("config/default").profile("prod");
+        assert_eq!(loader.profile_path().as_deref(), Some("config/prod"));
+    }
+
+    #[test]
+    fn test_layered_precedence() {
+        let settings: LayeredSettings = SettingsLoader::new("tests/config/default.toml")
+            .default("server.workers", 4)
+            .profile("dev")
+            .profile_env("SERVICE_UTILS_TEST_UNUSED_PROFILE")
+            .local_override("tests/config/local.toml")
+            .load()
+            .unwrap();
+
+        assert_eq!(settings.name, "base");
+        assert_eq!(settings.server.host, "127.0.0.1"); // from the profile file
+        assert_eq!(settings.server.port, 9000); // from the local override
+        assert_eq!(settings.server.workers, 4); // from the defaults
+    }
+
+    #[test]
+    fn test_missing_profile_and_override_are_optional() {
+        let settings: LayeredSettings = SettingsLoader::new("tests/config/default.toml")
+            .default("server.workers", 1)
+            .profile("missing")
+            .local_override("tests/config/missing.toml")
+            .load()
+            .unwrap();
+
+        assert_eq!(settings.server.host, "0.0.0.0");
+        assert_eq!(settings.server.port, 8080);
+    }
+
+    #[test]
+    fn test_load_and_validate_reports_all_errors() {
+        let err = SettingsLoader::new("tests/config/default.toml")
+            .default("server.workers", 0)
+            .load_and_validate::<LayeredSettings>()
+            .unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains("name: must be overridden"));
+        assert!(message.contains("server.workers: must be greater than 0"));
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        // SAFETY: the variables use a prefix unique to this test.
+        unsafe {
+            std::env::set_var("SUR_ENV_TEST__SERVER__PORT", "7000");
+            std::env::set_var("SUR_ENV_TEST__NAME", "from-env");
+        }
+
+        let settings: LayeredSettings = SettingsLoader::new("tests/config/default.toml")
+            .default("server.workers", 2)
+            .profile("dev")
+            .env_prefix("SUR_ENV_TEST")
+            .load()
+            .unwrap();
+
+        assert_eq!(settings.name, "from-env");
+        assert_eq!(settings.server.host, "127.0.0.1");
+        assert_eq!(settings.server.port, 7000);
+    }
+
+    #[test]
+    fn test_formats_deserialize_identically() {
+        let expected: FormatSettings = load_settings("tests/config/formats/settings.toml").unwrap();
+        assert_eq!(expected.timeout.as_secs(), 5400);
+        assert_eq!(expected.tags, ["alpha", "beta"]);
+
+        for (file, format) in [
+            ("settings.yaml", ConfigFormat::Yaml),
+            ("settings.json", ConfigFormat::Json),
+            ("settings.json5", ConfigFormat::Json5),
+        ] {
+            let path = format!("tests/config/formats/{}", file);
+            let guessed: FormatSettings = load_settings(&path).unwrap();
+            assert_eq!(guessed, expected, "{}", file);
+
+            let explicit: FormatSettings =
+                SettingsLoader::new(&path).format(format).load().unwrap();
+            assert_eq!(explicit, expected, "{}", file);
+
+            let content = std::fs::read_to_string(&path).unwrap();
+            let from_str: FormatSettings = load_settings_from_str(&content, format).unwrap();
+            assert_eq!(from_str, expected, "{}", file);
+        }
+    }
+
+    #[test]
+    fn test_explicit_format_ignores_extension() {
+        let path = "tests/config/formats/settings_yaml.conf";
+        assert!(load_settings::<FormatSettings>(path).is_err());
+
+        let settings: FormatSettings = SettingsLoader::new(path)
+            .format(ConfigFormat::Yaml)
+            .load()
+            .unwrap();
+        assert_eq!(settings.server.port, 8080);
+    }
+
+    #[test]
+    fn test_embedded_defaults() {
+        let settings: LayeredSettings = SettingsLoader::from_string(
+            include_str!("../../tests/config/default.toml"),
+            ConfigFormat::Toml,
+        )
+        .defaults_str("[server]\nworkers = 8\nport = 1", ConfigFormat::Toml)
+        .load()
+        .unwrap();
+
+        assert_eq!(settings.server.workers, 8); // from the embedded defaults
+        assert_eq!(settings.server.port, 8080); // from the base string
+        assert!(
+            SettingsLoader::from_string("", ConfigFormat::Toml)
+                .profile("dev")
+                .profile_path()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(
+            ConfigFormat::from_extension("YML"),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(
+            ConfigFormat::from_extension("json5"),
+            Some(ConfigFormat::Json5)
+        );
+        assert_eq!(ConfigFormat::from_extension("ini"), None);
+    }
 }
@@ -1,11 +1,25 @@
+pub mod config_dump;
+pub mod config_schema;
 pub mod config_util;
+pub mod crypto_util;
 
 #[cfg(feature = "request")]
 pub mod request;
 
+pub mod secret;
+pub mod settings_watcher;
 pub mod string_util;
+pub mod units;
+pub mod validate;
 
-pub use config_util::load_settings;
+pub use config_dump::EffectiveConfig;
+pub use config_util::{
+    ConfigFormat, SettingsLoader, load_and_validate, load_settings, load_settings_from_str,
+};
 #[cfg(feature = "request")]
 pub use request::*;
+pub use secret::Secret;
+pub use settings_watcher::SettingsWatcher;
 pub use string_util::*;
+pub use units::{ByteSize, ConfigDuration};
+pub use validate::{Validate, ValidationErrors};
@@ -26,7 +26,7 @@ impl HeaderMap {
     /// Insert a header key-value pair.
     pub fn insert(&mut self, key: &'static str, value: String) -> Result<()> {
         let header_value = reqwest::header::HeaderValue::from_str(&value)
-            .map_err(|_| Error::ErrorMessage("invalid headerValue".into()))?;
+            .map_err(|e| Error::ErrorMessage(format!("invalid value for header {}: {}", key, e)))?;
         self.headers.insert(key, header_value);
         Ok(())
     }
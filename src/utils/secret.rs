@@ -0,0 +1,190 @@
+use std::fmt;
+
+use config::{ConfigError, Value, ValueKind};
+use serde::Deserialize;
+
+use crate::error::Result;
+
+/// Prefix of values read from an environment variable, e.g. `env://JWT_ACCESS_SECRET`.
+pub const ENV_REF_PREFIX: &str = "env://";
+
+/// Prefix of values read from a file, e.g. `file:///run/secrets/db_password`.
+pub const FILE_REF_PREFIX: &str = "file://";
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Wrapper for sensitive values whose `Debug` and `Display` never print the value.
+///
+/// Deserializes transparently from the wrapped type, so it can be used directly in config
+/// structs.
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    /// Wraps a sensitive value.
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Returns a reference to the wrapped value.
+    pub fn expose_secret(&self) -> &T {
+        &self.0
+    }
+
+    /// Consumes the wrapper and returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Replaces secret references in the config tree.
+///
+/// Only strings that are a whole reference are replaced:
+///
+/// * `env://NAME` - the value of the environment variable `NAME`
+/// * `file://PATH` - the content of the file at `PATH`, without the trailing newline
+pub fn resolve_secret_refs(value: &mut Value) -> Result<()> {
+    resolve_value(value, "")
+}
+
+fn resolve_value(value: &mut Value, path: &str) -> Result<()> {
+    match &mut value.kind {
+        ValueKind::String(s) => {
+            let resolved =
+                resolve_str(s).map_err(|e| ConfigError::Message(format!("{}: {}", path, e)))?;
+            *s = resolved;
+        }
+        ValueKind::Table(table) => {
+            for (key, child) in table.iter_mut() {
+                let child_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                resolve_value(child, &child_path)?;
+            }
+        }
+        ValueKind::Array(items) => {
+            for (i, child) in items.iter_mut().enumerate() {
+                resolve_value(child, &format!("{}[{}]", path, i))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn resolve_str(raw: &str) -> core::result::Result<String, String> {
+    if let Some(name) = raw.strip_prefix(ENV_REF_PREFIX) {
+        return std::env::var(name)
+            .map_err(|_| format!("environment variable {} is not set", name));
+    }
+    if let Some(path) = raw.strip_prefix(FILE_REF_PREFIX) {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read secret file {}: {}", path, e))?;
+        return Ok(content.trim_end_matches(['\r', '\n']).to_string());
+    }
+    Ok(raw.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, File, FileFormat};
+
+    use super::*;
+
+    fn load(toml: &str) -> Result<Value> {
+        let mut value = Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()?
+            .cache;
+        resolve_secret_refs(&mut value)?;
+        Ok(value)
+    }
+
+    #[test]
+    fn test_secret_is_redacted() {
+        let secret = Secret::<String>::from("hunter2");
+        assert_eq!(format!("{}", secret), "[REDACTED]");
+        assert_eq!(format!("{:?}", secret), "Secret([REDACTED])");
+        assert_eq!(secret.expose_secret(), "hunter2");
+    }
+
+    #[test]
+    fn test_resolve_env_refs() {
+        // SAFETY: the variable name is unique to this test.
+        unsafe { std::env::set_var("SUR_SECRET_TEST_TOKEN", "s3cr3t") };
+
+        let value = load(
+            r#"
+            plain = "value"
+            from_env = "env://SUR_SECRET_TEST_TOKEN"
+            [nested]
+            url = "ws://user:${SUR_SECRET_TEST_TOKEN}@localhost"
+            prefixed = "see env://SUR_SECRET_TEST_TOKEN"
+            "#,
+        )
+        .unwrap();
+        let table = value.into_table().unwrap();
+        assert_eq!(table["plain"].clone().into_string().unwrap(), "value");
+        assert_eq!(table["from_env"].clone().into_string().unwrap(), "s3cr3t");
+        let nested = table["nested"].clone().into_table().unwrap();
+        // Only whole values are references.
+        assert_eq!(
+            nested["url"].clone().into_string().unwrap(),
+            "ws://user:${SUR_SECRET_TEST_TOKEN}@localhost"
+        );
+        assert_eq!(
+            nested["prefixed"].clone().into_string().unwrap(),
+            "see env://SUR_SECRET_TEST_TOKEN"
+        );
+    }
+
+    #[test]
+    fn test_resolve_file_ref() {
+        let path = std::env::temp_dir().join(format!("sur_secret_{}", std::process::id()));
+        std::fs::write(&path, "from-file\n").unwrap();
+
+        let value = load(&format!("password = \"file://{}\"", path.display())).unwrap();
+        let table = value.into_table().unwrap();
+        assert_eq!(
+            table["password"].clone().into_string().unwrap(),
+            "from-file"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_ref_reports_key() {
+        let err = load("[db]\npassword = \"env://SUR_SECRET_TEST_MISSING\"").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("db.password"));
+        assert!(message.contains("SUR_SECRET_TEST_MISSING"));
+    }
+}
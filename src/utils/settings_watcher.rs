@@ -0,0 +1,244 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use serde::de::DeserializeOwned;
+use tokio::{sync::watch, task::JoinHandle, time};
+
+use super::config_util::SettingsLoader;
+use crate::error::Result;
+
+/// Extensions tried, in order, for file sources given without one.
+const KNOWN_EXTENSIONS: [&str; 7] = ["toml", "json", "yaml", "yml", "json5", "ini", "ron"];
+
+/// Modification time, length and content hash of a watched file, `None` when the file is
+/// missing.
+type Fingerprint = Vec<Option<(SystemTime, u64, u64)>>;
+
+struct Shared<T> {
+    loader: SettingsLoader,
+    sender: watch::Sender<Arc<T>>,
+    last_error: Mutex<Option<String>>,
+}
+
+impl<T> Shared<T>
+where
+    T: DeserializeOwned,
+{
+    fn reload(&self) -> Result<()> {
+        match self.loader.load::<T>() {
+            Ok(settings) => {
+                self.sender.send_replace(Arc::new(settings));
+                *self.last_error.lock().unwrap() = None;
+                Ok(())
+            }
+            Err(e) => {
+                *self.last_error.lock().unwrap() = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Settings handle that reloads itself when the underlying config files change.
+///
+/// The files are polled at a fixed interval. A reload that fails keeps the previous value; the
+/// error is logged and available through [`SettingsWatcher::last_error`].
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use serde::Deserialize;
+/// use service_utils_rs::utils::{SettingsLoader, SettingsWatcher};
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Settings {
+///     allowed_origins: Vec<String>,
+/// }
+///
+/// # async fn run() -> service_utils_rs::error::Result<()> {
+/// let loader = SettingsLoader::new("config/default.toml");
+/// let watcher = SettingsWatcher::<Settings>::new(loader, Duration::from_secs(5))?;
+///
+/// let mut rx = watcher.subscribe();
+/// while rx.changed().await.is_ok() {
+///     println!("origins: {:?}", rx.borrow().allowed_origins);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct SettingsWatcher<T> {
+    shared: Arc<Shared<T>>,
+    task: JoinHandle<()>,
+}
+
+impl<T> SettingsWatcher<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    /// Loads the settings and starts polling the loader's files every `interval`.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn new(loader: SettingsLoader, interval: Duration) -> Result<Self> {
+        let last = fingerprint(&loader);
+        let settings = loader.load::<T>()?;
+        let (sender, _) = watch::channel(Arc::new(settings));
+        let shared = Arc::new(Shared {
+            loader,
+            sender,
+            last_error: Mutex::new(None),
+        });
+        let task = tokio::spawn(poll_loop(shared.clone(), last, interval));
+        Ok(Self { shared, task })
+    }
+
+    /// Returns a snapshot of the current settings.
+    pub fn current(&self) -> Arc<T> {
+        self.shared.sender.borrow().clone()
+    }
+
+    /// Returns a receiver notified each time the settings are successfully reloaded.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<T>> {
+        self.shared.sender.subscribe()
+    }
+
+    /// Reloads the settings immediately, keeping the previous value on failure.
+    pub fn reload(&self) -> Result<()> {
+        self.shared.reload()
+    }
+
+    /// Returns the error of the last reload, or `None` if it succeeded.
+    pub fn last_error(&self) -> Option<String> {
+        self.shared.last_error.lock().unwrap().clone()
+    }
+}
+
+impl<T> Drop for SettingsWatcher<T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn poll_loop<T>(shared: Arc<Shared<T>>, mut last: Fingerprint, interval: Duration)
+where
+    T: DeserializeOwned,
+{
+    let mut ticker = time::interval(interval);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let current = fingerprint(&shared.loader);
+        if current == last {
+            continue;
+        }
+        last = current;
+        if let Err(e) = shared.reload() {
+            eprintln!("Failed to reload settings, keeping previous value: {}", e);
+        }
+    }
+}
+
+fn fingerprint(loader: &SettingsLoader) -> Fingerprint {
+    loader
+        .file_sources()
+        .iter()
+        .map(|name| {
+            let path = resolve_file(name)?;
+            let meta = std::fs::metadata(&path).ok()?;
+            // Edits within the mtime resolution may keep both the time and the length.
+            let mut hasher = DefaultHasher::new();
+            std::fs::read(&path).ok()?.hash(&mut hasher);
+            Some((meta.modified().ok()?, meta.len(), hasher.finish()))
+        })
+        .collect()
+}
+
+/// Resolves a file source name to the file the `config` crate would read.
+fn resolve_file(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    KNOWN_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_extension(ext))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Settings {
+        limit: u32,
+    }
+
+    fn write_config(path: &Path, content: &str) {
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_on_change() {
+        let dir = std::env::temp_dir().join(format!("sur_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.toml");
+        write_config(&path, "limit = 1\n");
+
+        let loader = SettingsLoader::new(path.to_str().unwrap());
+        let watcher = SettingsWatcher::<Settings>::new(loader, Duration::from_millis(20)).unwrap();
+        let mut rx = watcher.subscribe();
+        assert_eq!(watcher.current().limit, 1);
+
+        write_config(&path, "limit = 100\n");
+        time::timeout(Duration::from_secs(5), rx.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rx.borrow().limit, 100);
+        assert_eq!(watcher.current().limit, 100);
+
+        write_config(&path, "limit = \"not a number\"\n");
+        assert!(watcher.reload().is_err());
+        assert!(watcher.last_error().is_some());
+        assert_eq!(watcher.current().limit, 100);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint_detects_same_size_change() {
+        let path = std::env::temp_dir().join(format!("sur_fp_{}.toml", std::process::id()));
+        write_config(&path, "limit = 1\n");
+        let loader = SettingsLoader::new(path.to_str().unwrap());
+        let before = fingerprint(&loader);
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        write_config(&path, "limit = 2\n");
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_ne!(fingerprint(&loader), before);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_file_guesses_extension() {
+        assert_eq!(
+            resolve_file("tests/test_config"),
+            Some(PathBuf::from("tests/test_config.toml"))
+        );
+        assert_eq!(resolve_file("tests/does_not_exist"), None);
+    }
+}
@@ -0,0 +1,388 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+
+/// Duration for configuration files, written as `"15m"`, `"7d"`, `"1h30m"` or `"3600s"`.
+///
+/// Plain integers (and integer strings) are read as seconds, so existing values such as
+/// `access_token_duration = 10800` keep their meaning.
+///
+/// Supported units: `ms`, `s`, `m`, `h`, `d` and `w`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConfigDuration(Duration);
+
+impl ConfigDuration {
+    /// Creates a duration from whole seconds.
+    pub const fn from_secs(secs: u64) -> Self {
+        Self(Duration::from_secs(secs))
+    }
+
+    /// Returns the duration in whole seconds.
+    pub const fn as_secs(&self) -> u64 {
+        self.0.as_secs()
+    }
+
+    /// Returns the underlying `std::time::Duration`.
+    pub const fn as_duration(&self) -> Duration {
+        self.0
+    }
+
+    /// Returns `true` if the duration is zero.
+    pub const fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl From<Duration> for ConfigDuration {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<ConfigDuration> for Duration {
+    fn from(duration: ConfigDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for ConfigDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(secs) = s.parse::<u64>() {
+            return Ok(Self::from_secs(secs));
+        }
+
+        let mut total = Duration::ZERO;
+        for (value, unit) in split_units(s)? {
+            let unit_millis: u64 = match unit {
+                "ms" => 1,
+                "s" => 1_000,
+                "m" => 60_000,
+                "h" => 3_600_000,
+                "d" => 86_400_000,
+                "w" => 604_800_000,
+                _ => return Err(format!("unknown duration unit `{}` in `{}`", unit, s)),
+            };
+            let millis = value
+                .checked_mul(unit_millis)
+                .ok_or_else(|| format!("duration `{}` is too large", s))?;
+            total += Duration::from_millis(millis);
+        }
+        Ok(Self(total))
+    }
+}
+
+impl fmt::Display for ConfigDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.0.as_millis();
+        if millis == 0 {
+            return f.write_str("0s");
+        }
+        if !millis.is_multiple_of(1_000) {
+            return write!(f, "{}ms", millis);
+        }
+        let mut secs = self.0.as_secs();
+        for (unit, size) in [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)] {
+            if secs >= size {
+                write!(f, "{}{}", secs / size, unit)?;
+                secs %= size;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for ConfigDuration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigDuration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(UnitVisitor::<Self>::new(
+            "a duration like \"15m\" or seconds",
+        ))
+    }
+}
+
+/// Size in bytes for configuration files, written as `"4MiB"`, `"512KB"` or `"100B"`.
+///
+/// Plain integers (and integer strings) are read as bytes. Decimal units (`KB`, `MB`, `GB`,
+/// `TB`) are powers of 1000, binary units (`KiB`, `MiB`, `GiB`, `TiB`) powers of 1024. Units are
+/// case-insensitive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    /// Creates a size from a number of bytes.
+    pub const fn from_bytes(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    /// Returns the size in bytes.
+    pub const fn as_bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for ByteSize {
+    fn from(bytes: u64) -> Self {
+        Self(bytes)
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(bytes) = s.parse::<u64>() {
+            return Ok(Self(bytes));
+        }
+
+        let units = split_units(s)?;
+        let [(value, unit)] = units.as_slice() else {
+            return Err(format!("invalid byte size `{}`", s));
+        };
+        let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+            "b" => 1,
+            "kb" => 1_000,
+            "mb" => 1_000_000,
+            "gb" => 1_000_000_000,
+            "tb" => 1_000_000_000_000,
+            "kib" => 1 << 10,
+            "mib" => 1 << 20,
+            "gib" => 1 << 30,
+            "tib" => 1 << 40,
+            _ => return Err(format!("unknown size unit `{}` in `{}`", unit, s)),
+        };
+        value
+            .checked_mul(multiplier)
+            .map(Self)
+            .ok_or_else(|| format!("byte size `{}` is too large", s))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (unit, size) in [
+            ("TiB", 1u64 << 40),
+            ("GiB", 1 << 30),
+            ("MiB", 1 << 20),
+            ("KiB", 1 << 10),
+        ] {
+            if self.0 >= size && self.0.is_multiple_of(size) {
+                return write!(f, "{}{}", self.0 / size, unit);
+            }
+        }
+        write!(f, "{}B", self.0)
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(UnitVisitor::<Self>::new("a size like \"4MiB\" or bytes"))
+    }
+}
+
+/// Splits `"1h30m"` into `[(1, "h"), (30, "m")]`.
+fn split_units(s: &str) -> Result<Vec<(u64, &str)>, String> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(format!("expected a number in `{}`", s));
+        }
+        let value = rest[.. digits]
+            .parse::<u64>()
+            .map_err(|e| format!("invalid number in `{}`: {}", s, e))?;
+        rest = rest[digits ..].trim_start();
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .unwrap_or(rest.len());
+        if unit_len == 0 {
+            return Err(format!("missing unit in `{}`", s));
+        }
+        parts.push((value, &rest[.. unit_len]));
+        rest = rest[unit_len ..].trim_start();
+    }
+    if parts.is_empty() {
+        return Err("empty value".to_string());
+    }
+    Ok(parts)
+}
+
+/// Accepts either a non-negative integer or a string parsed with `FromStr`.
+struct UnitVisitor<T> {
+    expecting: &'static str,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T> UnitVisitor<T> {
+    fn new(expecting: &'static str) -> Self {
+        Self {
+            expecting,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T> Visitor<'_> for UnitVisitor<T>
+where
+    T: FromStr<Err = String> + FromBaseUnit,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.expecting)
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(T::from_base_unit(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        u64::try_from(v)
+            .map(T::from_base_unit)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        v.parse().map_err(E::custom)
+    }
+}
+
+/// Conversion from a bare integer, in the type's base unit (seconds or bytes).
+trait FromBaseUnit {
+    fn from_base_unit(value: u64) -> Self;
+}
+
+impl FromBaseUnit for ConfigDuration {
+    fn from_base_unit(value: u64) -> Self {
+        Self::from_secs(value)
+    }
+}
+
+impl FromBaseUnit for ByteSize {
+    fn from_base_unit(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Limits {
+        timeout: ConfigDuration,
+        max_body: ByteSize,
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!("3600".parse(), Ok(ConfigDuration::from_secs(3600)));
+        assert_eq!("3600s".parse(), Ok(ConfigDuration::from_secs(3600)));
+        assert_eq!("15m".parse(), Ok(ConfigDuration::from_secs(900)));
+        assert_eq!("7d".parse(), Ok(ConfigDuration::from_secs(604_800)));
+        assert_eq!("1h30m".parse(), Ok(ConfigDuration::from_secs(5400)));
+        assert_eq!(
+            "250ms".parse(),
+            Ok(ConfigDuration::from(Duration::from_millis(250)))
+        );
+        assert!("15 minutes".parse::<ConfigDuration>().is_err());
+        assert!("m".parse::<ConfigDuration>().is_err());
+        assert!("".parse::<ConfigDuration>().is_err());
+    }
+
+    #[test]
+    fn test_display_duration() {
+        assert_eq!(ConfigDuration::from_secs(5400).to_string(), "1h30m");
+        assert_eq!(ConfigDuration::from_secs(604_800).to_string(), "7d");
+        assert_eq!(ConfigDuration::from_secs(0).to_string(), "0s");
+        assert_eq!(
+            ConfigDuration::from(Duration::from_millis(1500)).to_string(),
+            "1500ms"
+        );
+    }
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!("100".parse(), Ok(ByteSize::from_bytes(100)));
+        assert_eq!("4MiB".parse(), Ok(ByteSize::from_bytes(4 * 1024 * 1024)));
+        assert_eq!("512KB".parse(), Ok(ByteSize::from_bytes(512_000)));
+        assert_eq!("1gib".parse(), Ok(ByteSize::from_bytes(1 << 30)));
+        assert!("4XB".parse::<ByteSize>().is_err());
+        assert!("1MiB2KiB".parse::<ByteSize>().is_err());
+        assert_eq!(ByteSize::from_bytes(4 << 20).to_string(), "4MiB");
+        assert_eq!(ByteSize::from_bytes(1000).to_string(), "1000B");
+    }
+
+    #[test]
+    fn test_deserialize_from_config() {
+        let limits: Limits = config::Config::builder()
+            .add_source(config::File::from_str(
+                "timeout = \"15m\"\nmax_body = \"4MiB\"",
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(limits.timeout.as_secs(), 900);
+        assert_eq!(limits.max_body.as_bytes(), 4 << 20);
+
+        let limits: Limits = config::Config::builder()
+            .add_source(config::File::from_str(
+                "timeout = 30\nmax_body = 1024",
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(limits.timeout.as_secs(), 30);
+        assert_eq!(limits.max_body.as_bytes(), 1024);
+
+        let err = serde_json::from_str::<ConfigDuration>("-5").unwrap_err();
+        assert!(err.to_string().contains("invalid value"));
+    }
+}
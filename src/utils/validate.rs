@@ -0,0 +1,195 @@
+use std::fmt;
+
+use crate::error::{Error, Result};
+
+/// A single problem found while validating configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Dotted key path of the offending value, e.g. `jwt.access_token_duration`.
+    pub path: String,
+    pub message: String,
+}
+
+/// Collects every validation problem together with its dotted key path.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationErrors {
+    prefix: Vec<String>,
+    errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a problem for `field`, relative to the current nesting level.
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        let path = self
+            .prefix
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(field))
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(".");
+        self.errors.push(FieldError {
+            path,
+            message: message.into(),
+        });
+    }
+
+    /// Records a problem for `field` unless `condition` holds.
+    pub fn ensure(&mut self, condition: bool, field: &str, message: impl Into<String>) {
+        if !condition {
+            self.add(field, message);
+        }
+    }
+
+    /// Validates a nested section, prefixing its problems with `field`.
+    pub fn nested<V>(&mut self, field: &str, value: &V)
+    where
+        V: Validate + ?Sized,
+    {
+        self.prefix.push(field.to_string());
+        value.validate(self);
+        self.prefix.pop();
+    }
+
+    /// Returns `true` if no problem was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns the recorded problems in the order they were found.
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// Converts the collection into `Ok(())` if empty, or `Error::ValidationError` otherwise.
+    pub fn into_result(self) -> Result<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::ValidationError(self))
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration ({} problems)", self.errors.len())?;
+        for error in &self.errors {
+            write!(f, "\n  {}: {}", error.path, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Semantic checks for configuration structs, run after deserialization.
+///
+/// # Example
+///
+/// ```
+/// use service_utils_rs::utils::validate::{Validate, ValidationErrors};
+///
+/// struct ServerCfg {
+///     port: u16,
+/// }
+///
+/// impl Validate for ServerCfg {
+///     fn validate(&self, errors: &mut ValidationErrors) {
+///         errors.ensure(self.port != 0, "port", "must not be 0");
+///     }
+/// }
+///
+/// let mut errors = ValidationErrors::new();
+/// errors.nested("server", &ServerCfg { port: 0 });
+/// assert_eq!(errors.errors()[0].path, "server.port");
+/// ```
+pub trait Validate {
+    /// Records every problem found in `self` into `errors`.
+    fn validate(&self, errors: &mut ValidationErrors);
+
+    /// Validates `self`, returning all problems at once.
+    fn validate_all(&self) -> Result<()> {
+        let mut errors = ValidationErrors::new();
+        self.validate(&mut errors);
+        errors.into_result()
+    }
+}
+
+impl<V> Validate for Option<V>
+where
+    V: Validate,
+{
+    fn validate(&self, errors: &mut ValidationErrors) {
+        if let Some(value) = self {
+            value.validate(errors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ServerCfg {
+        host: String,
+        port: u16,
+    }
+
+    impl Validate for ServerCfg {
+        fn validate(&self, errors: &mut ValidationErrors) {
+            errors.ensure(!self.host.is_empty(), "host", "must not be empty");
+            errors.ensure(self.port != 0, "port", "must not be 0");
+        }
+    }
+
+    struct Settings {
+        http: ServerCfg,
+        websocket: Option<ServerCfg>,
+    }
+
+    impl Validate for Settings {
+        fn validate(&self, errors: &mut ValidationErrors) {
+            errors.nested("http", &self.http);
+            errors.nested("websocket", &self.websocket);
+        }
+    }
+
+    #[test]
+    fn test_collects_all_errors_with_paths() {
+        let settings = Settings {
+            http: ServerCfg {
+                host: String::new(),
+                port: 0,
+            },
+            websocket: Some(ServerCfg {
+                host: "0.0.0.0".to_string(),
+                port: 0,
+            }),
+        };
+
+        let err = settings.validate_all().unwrap_err();
+        let Error::ValidationError(errors) = err else {
+            panic!("Expected Error::ValidationError");
+        };
+        let paths: Vec<&str> = errors.errors().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["http.host", "http.port", "websocket.port"]);
+        assert!(errors.to_string().contains("http.port: must not be 0"));
+    }
+
+    #[test]
+    fn test_valid_settings() {
+        let settings = Settings {
+            http: ServerCfg {
+                host: "0.0.0.0".to_string(),
+                port: 8080,
+            },
+            websocket: None,
+        };
+
+        assert!(settings.validate_all().is_ok());
+    }
+}